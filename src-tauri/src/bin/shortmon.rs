@@ -0,0 +1,349 @@
+use std::{collections::HashMap, env, process};
+
+use app::monitor::{
+    backend::{BackendRegistry, EnumeratedDisplay},
+    capabilities::MonitorCapabilities,
+    features::{self, FeatureKind},
+    info::{CapabilitiesStatus, MonitorInfo},
+    input::{input_name, MonitorInput},
+    mccs::{self, ParseMode},
+};
+
+const USAGE: &str = "Usage: shortmon [--json] [--demo] [--force] <command>
+
+Commands:
+    list                          List monitors and their inputs
+    inputs <monitor>              List the inputs of a monitor
+    switch <monitor> <input>      Switch a monitor to an input
+    get <monitor> <vcp>           Read a VCP feature
+    set <monitor> <vcp> <value>   Write a VCP feature
+    check <capabilities>          Show where a capability string is malformed
+
+<monitor> is a monitor id or its position in `list`, starting at 1.
+<input> is an input name (HDMI1, \"DP 1\") or its hex VCP 0x60 value (11, 0x11).
+<vcp> is a hex feature code (10, 0x10), <value> is decimal or 0x prefixed hex.
+Features a monitor doesn't list in its capabilities are refused without --force.";
+
+enum Command {
+    List,
+    Inputs(String),
+    Switch(String, String),
+    Get(String, String),
+    Set(String, String, String),
+    Check(String),
+}
+
+impl Command {
+    fn parse(args: &[String]) -> Option<Command> {
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+
+        match args.as_slice() {
+            ["list"] => Some(Command::List),
+            ["inputs", monitor] => Some(Command::Inputs(monitor.to_string())),
+            ["switch", monitor, input] => {
+                Some(Command::Switch(monitor.to_string(), input.to_string()))
+            }
+            ["get", monitor, vcp] => Some(Command::Get(monitor.to_string(), vcp.to_string())),
+            ["set", monitor, vcp, value] => Some(Command::Set(
+                monitor.to_string(),
+                vcp.to_string(),
+                value.to_string(),
+            )),
+            ["check", capabilities] => Some(Command::Check(capabilities.to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct VcpReading {
+    code: u8,
+    current: u16,
+    maximum: u16,
+    kind: FeatureKind,
+}
+
+#[derive(serde::Serialize)]
+struct VcpWritten {
+    code: u8,
+    value: u16,
+}
+
+#[derive(serde::Serialize)]
+struct CapabilitiesCheck {
+    error: Option<String>,
+    offset: Option<usize>,
+    /// Repairs lenient parsing has to make, as done for real monitors
+    warnings: Vec<String>,
+    /// The capability string as parsed leniently, in canonical form
+    canonical: Option<String>,
+}
+
+fn find_monitor<'a>(
+    monitors: &'a [EnumeratedDisplay],
+    selector: &str,
+) -> Result<&'a EnumeratedDisplay, String> {
+    if let Some(monitor) = monitors
+        .iter()
+        .find(|m| m.display.id().to_string() == selector)
+    {
+        return Ok(monitor);
+    }
+
+    selector
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|i| monitors.get(i))
+        .ok_or(format!("no monitor matches '{}'", selector))
+}
+
+fn parse_value(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse::<u16>(),
+    }
+    .map_err(|_| format!("invalid value '{}'", s))
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+
+    Ok(())
+}
+
+fn format_inputs(
+    inputs: &[MonitorInput],
+    names: &HashMap<MonitorInput, String>,
+    current: Option<MonitorInput>,
+) -> String {
+    inputs
+        .iter()
+        .map(|input| {
+            let name = names.get(input).cloned().unwrap_or(input.to_string());
+
+            match current {
+                Some(current) if current == *input => format!("{}*", name),
+                _ => name,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn check_capabilities(cap_string: String, json: bool) -> Result<(), String> {
+    let strict = MonitorCapabilities::from_cap_string(cap_string.clone()).err();
+    let lenient = MonitorCapabilities::from_cap_string_with(cap_string.clone(), ParseMode::Lenient);
+    let canonical = lenient.as_ref().ok().map(|caps| caps.to_cap_string());
+    let warnings = lenient.map(|caps| caps.warnings).unwrap_or_default();
+
+    if json {
+        return print_json(&CapabilitiesCheck {
+            error: strict.as_ref().map(|e| e.to_string()),
+            offset: strict.as_ref().and_then(|e| e.offset),
+            warnings,
+            canonical,
+        });
+    }
+
+    match strict {
+        Some(e) => println!("{}\n{}", e, e.render(&cap_string)),
+        None => println!("Capability string is valid"),
+    }
+
+    for warning in warnings {
+        println!("repaired: {}", warning);
+    }
+
+    if let Some(canonical) = canonical {
+        println!("canonical: {}", canonical);
+    }
+
+    Ok(())
+}
+
+fn run(command: Command, json: bool, force: bool) -> Result<(), String> {
+    if let Command::Check(cap_string) = command {
+        return check_capabilities(cap_string, json);
+    }
+
+    let mut registry = BackendRegistry::from_env();
+
+    for e in registry.quirks_mut().load_user_files() {
+        eprintln!("{}", e);
+    }
+
+    let enumeration = registry.enumerate();
+
+    for (backend, e) in &enumeration.errors {
+        eprintln!("Unable to enumerate {} monitors: {}", backend, e);
+    }
+
+    let monitors = enumeration.displays;
+
+    match command {
+        Command::List => {
+            let info_list: Vec<MonitorInfo> = monitors.iter().map(MonitorInfo::from).collect();
+
+            if json {
+                return print_json(&info_list);
+            }
+
+            for (i, info) in info_list.iter().enumerate() {
+                println!("{}. {} [{}]", i + 1, info.model, info.id);
+
+                if let CapabilitiesStatus::Unavailable { reason } = &info.capabilities {
+                    println!("   capabilities unavailable: {}", reason);
+                }
+
+                println!(
+                    "   inputs: {}",
+                    format_inputs(&info.inputs, &info.input_names, info.current_input)
+                );
+
+                if let Some(brightness) = info.brightness {
+                    println!(
+                        "   brightness: {}/{}",
+                        brightness.current, brightness.maximum
+                    );
+                }
+
+                if let Some(contrast) = info.contrast {
+                    println!("   contrast: {}/{}", contrast.current, contrast.maximum);
+                }
+
+                if let Some(volume) = info.volume {
+                    let muted = if info.muted == Some(true) {
+                        " (muted)"
+                    } else {
+                        ""
+                    };
+
+                    println!("   volume: {}/{}{}", volume.current, volume.maximum, muted);
+                }
+            }
+        }
+        Command::Inputs(selector) => {
+            let monitor = find_monitor(&monitors, &selector)?;
+            let inputs = monitor.display.get_inputs().map_err(|e| e.to_string())?;
+
+            if json {
+                return print_json(&inputs);
+            }
+
+            let current = monitor.display.get_current_input().ok();
+            let capabilities = monitor.display.capabilities();
+
+            for input in inputs {
+                let name = input_name(capabilities, input);
+
+                match current {
+                    Some(current) if current == input => println!("{} (active)", name),
+                    _ => println!("{}", name),
+                }
+            }
+        }
+        Command::Switch(selector, input) => {
+            let monitor = find_monitor(&monitors, &selector)?;
+            let input = input.parse::<MonitorInput>().map_err(|e| e.to_string())?;
+
+            monitor
+                .display
+                .set_input(input)
+                .map_err(|e| e.to_string())?;
+
+            if json {
+                return print_json(&input);
+            }
+
+            println!("Switched {} to {}", monitor.display.id(), input);
+        }
+        Command::Get(selector, code) => {
+            let monitor = find_monitor(&monitors, &selector)?;
+            let code = mccs::parse_code(&code).map_err(|e| e.to_string())?;
+
+            let value = monitor
+                .display
+                .get_vcp(code, force)
+                .map_err(|e| e.to_string())?;
+
+            if json {
+                return print_json(&VcpReading {
+                    code,
+                    current: value.current,
+                    maximum: value.maximum,
+                    kind: value.kind,
+                });
+            }
+
+            // The monitor's own names take precedence over the standard ones
+            let capabilities = monitor.display.capabilities();
+            let definition = features::lookup(code);
+
+            let name = capabilities
+                .and_then(|c| c.feature_name(code))
+                .or(definition.map(|f| f.name))
+                .map(|name| format!(" ({})", name))
+                .unwrap_or_default();
+
+            let value_name = capabilities
+                .and_then(|c| c.value_name(code, value.current))
+                .or_else(|| match (value.kind, definition) {
+                    (FeatureKind::NonContinuous, Some(f)) => {
+                        f.value_name((value.current & 0xff) as u8)
+                    }
+                    _ => None,
+                })
+                .map(|name| format!(" ({})", name))
+                .unwrap_or_default();
+
+            println!(
+                "VCP 0x{:02X}{}: current {}{}, maximum {}",
+                code, name, value.current, value_name, value.maximum
+            );
+        }
+        Command::Set(selector, code, value) => {
+            let monitor = find_monitor(&monitors, &selector)?;
+            let code = mccs::parse_code(&code).map_err(|e| e.to_string())?;
+            let value = parse_value(&value)?;
+
+            monitor
+                .display
+                .set_vcp(code, value, force)
+                .map_err(|e| e.to_string())?;
+
+            if json {
+                return print_json(&VcpWritten { code, value });
+            }
+
+            println!("VCP 0x{:02X} set to {}", code, value);
+        }
+        Command::Check(_) => unreachable!(),
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    let json = args.iter().any(|a| a == "--json");
+    let force = args.iter().any(|a| a == "--force");
+
+    // --demo is picked up by BackendRegistry::from_env
+    args.retain(|a| a != "--json" && a != "--demo" && a != "--force");
+
+    let command = match Command::parse(&args) {
+        Some(command) => command,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(command, json, force) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
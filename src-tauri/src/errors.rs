@@ -1,9 +1,92 @@
-#[derive(Debug, Clone)]
-pub struct MonitorError(pub &'static str);
+use crate::monitor::identity::MonitorId;
+
+/// Everything that can go wrong talking to a monitor. Serialized with a `kind` tag
+/// so the frontend can tell failures apart, e.g. `{"kind": "timeout"}`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MonitorError {
+    /// The bus or the OS call failed
+    Io {
+        message: String,
+    },
+    /// Usually missing access to `/dev/i2c-*`
+    PermissionDenied {
+        message: String,
+    },
+    /// The monitor didn't answer in time
+    Timeout,
+    /// The monitor answered with a corrupt or unexpected reply
+    Protocol {
+        message: String,
+    },
+    /// The monitor doesn't implement a VCP feature
+    UnsupportedFeature {
+        code: u8,
+    },
+    /// The backend can't write with a non-standard DDC/CI source address
+    UnsupportedSourceAddress {
+        address: u8,
+    },
+    /// The capability string couldn't be read or parsed
+    CapabilityParse {
+        context: String,
+        message: String,
+    },
+    MonitorNotFound {
+        id: MonitorId,
+    },
+    /// An input name or code that doesn't map to a known input
+    InvalidInput {
+        input: String,
+    },
+    /// The monitor is in use elsewhere, e.g. a poisoned or already borrowed handle
+    Busy,
+    /// A profile applied by name, e.g. from a shortcut, that was renamed or deleted
+    ProfileNotFound {
+        name: String,
+    },
+}
+
+impl MonitorError {
+    pub fn io(message: impl ToString) -> MonitorError {
+        MonitorError::Io {
+            message: message.to_string(),
+        }
+    }
+
+    pub fn capability_parse(context: &str, message: impl ToString) -> MonitorError {
+        MonitorError::CapabilityParse {
+            context: context.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
 
 impl std::fmt::Display for MonitorError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Monitor Error: {}", self.0)
+        write!(f, "Monitor Error: ")?;
+
+        match self {
+            MonitorError::Io { message } => write!(f, "{}", message),
+            MonitorError::PermissionDenied { message } => {
+                write!(f, "permission denied ({})", message)
+            }
+            MonitorError::Timeout => write!(f, "monitor didn't respond in time"),
+            MonitorError::Protocol { message } => write!(f, "invalid DDC/CI reply ({})", message),
+            MonitorError::UnsupportedFeature { code } => {
+                write!(f, "VCP feature 0x{:02X} isn't supported", code)
+            }
+            MonitorError::UnsupportedSourceAddress { address } => {
+                write!(f, "can't write from source address 0x{:02X}", address)
+            }
+            MonitorError::CapabilityParse { context, message } => {
+                write!(f, "unable to parse capabilities, {} ({})", context, message)
+            }
+            MonitorError::MonitorNotFound { id } => write!(f, "monitor {} not found", id),
+            MonitorError::InvalidInput { input } => write!(f, "unknown input {}", input),
+            MonitorError::Busy => write!(f, "monitor is busy"),
+            MonitorError::ProfileNotFound { name } => write!(f, "profile {} not found", name),
+        }
     }
 }
 
@@ -12,3 +95,15 @@ impl std::error::Error for MonitorError {
         None
     }
 }
+
+impl From<std::io::Error> for MonitorError {
+    fn from(val: std::io::Error) -> Self {
+        match val.kind() {
+            std::io::ErrorKind::PermissionDenied => MonitorError::PermissionDenied {
+                message: val.to_string(),
+            },
+            std::io::ErrorKind::TimedOut => MonitorError::Timeout,
+            _ => MonitorError::io(val),
+        }
+    }
+}
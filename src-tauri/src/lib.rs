@@ -0,0 +1,5 @@
+pub mod errors;
+pub mod monitor;
+pub mod profile;
+pub mod settings;
+pub mod shortcut;
@@ -4,37 +4,181 @@
 )]
 
 use std::{
-    sync::mpsc::{self, Receiver, SyncSender},
+    collections::{HashMap, VecDeque},
+    fs,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender, SyncSender},
+        Mutex,
+    },
     thread,
 };
 
-use monitor::{input::MonitorInput, Monitor};
+use app::{
+    errors::MonitorError,
+    monitor::{
+        backend::{BackendRegistry, EnumeratedDisplay, VcpValue},
+        controls::{adjust_control, get_mute, set_mute, Adjustment, Control},
+        identity::MonitorId,
+        info::MonitorInfo,
+        input::MonitorInput,
+        power::{switch_input, PowerMode},
+    },
+    profile::{Profile, StepResult},
+    settings::Settings,
+    shortcut::{validate_accelerator, Shortcut, ShortcutAction},
+};
 use tauri::{
-    AppHandle, CustomMenuItem, Manager, RunEvent, SystemTray, SystemTrayEvent, SystemTrayMenu,
+    AppHandle, CustomMenuItem, GlobalShortcutManager, Manager, RunEvent, SystemTray,
+    SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
 };
 
-#[macro_use]
-extern crate num_derive;
-
-mod errors;
-mod monitor;
-
-#[derive(serde::Serialize, Clone)]
-struct MonitorInfo {
-    id: u8,
-    model: String,
-    inputs: Vec<MonitorInput>,
-}
+type Reply<T> = Sender<Result<T, MonitorError>>;
 
 enum MonitorManagerCommand {
     RefreshList(tauri::Window),
-    SwitchInput((u8, MonitorInput)),
+    SwitchInput((MonitorId, MonitorInput, Reply<()>)),
+    ApplyProfile((Profile, Reply<Vec<StepResult>>)),
+    CycleInput((MonitorId, Reply<MonitorInput>)),
+    GetVcp((MonitorId, u8, bool, Reply<VcpValue>)),
+    SetVcp((MonitorId, u8, u16, bool, Reply<()>)),
+    /// Requests can overtake each other on their way to the manager, the window
+    /// numbers its adjustments so the manager can tell which one is the latest
+    AdjustControl((MonitorId, Control, Adjustment, u64, Reply<u16>)),
+    GetPowerMode((MonitorId, Reply<PowerMode>)),
+    SetPowerMode((MonitorId, PowerMode, Reply<()>)),
+    SetAllPowerModes((PowerMode, Reply<()>)),
+    GetMute((MonitorId, Reply<bool>)),
+    SetMute((MonitorId, bool, Reply<()>)),
+    SetAllMutes((bool, Reply<()>)),
+    SetWakeOnSwitch(bool),
+}
+
+impl MonitorManager {
+    /// Send a command and wait for the manager thread to reply, without blocking
+    /// the async runtime.
+    async fn request<T: Send + 'static>(
+        &self,
+        command: impl FnOnce(Reply<T>) -> MonitorManagerCommand,
+    ) -> Result<T, MonitorError> {
+        let (reply, rx) = mpsc::channel();
+        let command = command(reply);
+        let tx = self.tx.clone();
+
+        // Sending blocks while the queue is full, which mustn't hold up the async runtime
+        tauri::async_runtime::spawn_blocking(move || {
+            tx.send(command)
+                .map_err(|_| MonitorError::io("Monitor manager stopped"))?;
+
+            rx.recv()
+                .map_err(|_| MonitorError::io("Monitor manager stopped"))?
+        })
+        .await
+        .map_err(MonitorError::io)?
+    }
 }
 
 struct MonitorManager {
     tx: SyncSender<MonitorManagerCommand>,
 }
 
+/// Problems found during setup, before the window is there to show them. The
+/// window collects them once it's loaded.
+#[derive(Default)]
+struct StartupErrors(Mutex<Vec<String>>);
+
+impl StartupErrors {
+    fn push(&self, message: String) {
+        eprintln!("{}", message);
+
+        if let Ok(mut errors) = self.0.lock() {
+            errors.push(message);
+        }
+    }
+}
+
+struct SettingsManager {
+    path: PathBuf,
+    settings: Mutex<Settings>,
+    /// False when the settings file couldn't be loaded nor copied aside, saving
+    /// would lose it
+    writable: bool,
+}
+
+impl SettingsManager {
+    /// A settings file that can't be loaded is copied aside before anything is
+    /// saved over it, and the defaults are used instead.
+    fn load(path: PathBuf, errors: &StartupErrors) -> SettingsManager {
+        let (settings, writable) = match Settings::load(&path) {
+            Ok(settings) => (settings, true),
+            Err(e) => {
+                let backup = path.with_extension("json.bak");
+                let writable = fs::copy(&path, &backup).is_ok();
+
+                errors.push(match writable {
+                    true => format!(
+                        "Unable to load {} ({}), it was copied to {} and the defaults are used",
+                        path.display(),
+                        e,
+                        backup.display()
+                    ),
+                    false => format!(
+                        "Unable to load {} ({}), changes to the settings won't be saved",
+                        path.display(),
+                        e
+                    ),
+                });
+
+                (Settings::default(), writable)
+            }
+        };
+
+        SettingsManager {
+            settings: Mutex::new(settings),
+            path,
+            writable,
+        }
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Settings) -> T) -> Result<T, String> {
+        let settings = self
+            .settings
+            .lock()
+            .map_err(|_| "Settings are poisoned".to_string())?;
+
+        Ok(f(&settings))
+    }
+
+    /// Apply a change to the settings and persist them
+    fn update<T>(&self, f: impl FnOnce(&mut Settings) -> Result<T, String>) -> Result<T, String> {
+        let mut settings = self
+            .settings
+            .lock()
+            .map_err(|_| "Settings are poisoned".to_string())?;
+
+        if !self.writable {
+            return Err(format!(
+                "{} couldn't be loaded, not saving over it",
+                self.path.display()
+            ));
+        }
+
+        let result = f(&mut settings)?;
+        settings.save(&self.path).map_err(|e| e.to_string())?;
+
+        Ok(result)
+    }
+}
+
+#[tauri::command]
+fn take_startup_errors(errors: tauri::State<'_, StartupErrors>) -> Vec<String> {
+    errors
+        .0
+        .lock()
+        .map(|mut errors| errors.drain(..).collect())
+        .unwrap_or_default()
+}
+
 #[tauri::command]
 fn refresh_monitor_info(state: tauri::State<'_, MonitorManager>, window: tauri::Window) {
     state
@@ -44,18 +188,443 @@ fn refresh_monitor_info(state: tauri::State<'_, MonitorManager>, window: tauri::
 }
 
 #[tauri::command]
-fn switch_monitor_input(
+async fn switch_monitor_input(
     state: tauri::State<'_, MonitorManager>,
-    monitor_idx: u8,
+    monitor_id: MonitorId,
     input: MonitorInput,
-) {
+) -> Result<(), MonitorError> {
+    state
+        .request(|reply| MonitorManagerCommand::SwitchInput((monitor_id, input, reply)))
+        .await
+}
+
+/// Read any VCP feature. Features the monitor doesn't list in its capabilities
+/// are refused unless `force` is set.
+#[tauri::command]
+async fn get_vcp_feature(
+    state: tauri::State<'_, MonitorManager>,
+    monitor_id: MonitorId,
+    code: u8,
+    force: Option<bool>,
+) -> Result<VcpValue, MonitorError> {
+    let force = force.unwrap_or(false);
+
+    state
+        .request(|reply| MonitorManagerCommand::GetVcp((monitor_id, code, force, reply)))
+        .await
+}
+
+#[tauri::command]
+async fn set_vcp_feature(
+    state: tauri::State<'_, MonitorManager>,
+    monitor_id: MonitorId,
+    code: u8,
+    value: u16,
+    force: Option<bool>,
+) -> Result<(), MonitorError> {
+    let force = force.unwrap_or(false);
+
+    state
+        .request(|reply| MonitorManagerCommand::SetVcp((monitor_id, code, value, force, reply)))
+        .await
+}
+
+#[tauri::command]
+async fn get_monitor_control(
+    state: tauri::State<'_, MonitorManager>,
+    monitor_id: MonitorId,
+    control: Control,
+) -> Result<VcpValue, MonitorError> {
+    state
+        .request(|reply| MonitorManagerCommand::GetVcp((monitor_id, control.code(), false, reply)))
+        .await
+}
+
+/// Set a slider control such as brightness or volume, returning the value written
+#[tauri::command]
+async fn adjust_monitor_control(
+    state: tauri::State<'_, MonitorManager>,
+    monitor_id: MonitorId,
+    control: Control,
+    adjustment: Adjustment,
+    sequence: u64,
+) -> Result<u16, MonitorError> {
+    state
+        .request(|reply| {
+            MonitorManagerCommand::AdjustControl((monitor_id, control, adjustment, sequence, reply))
+        })
+        .await
+}
+
+#[tauri::command]
+async fn get_monitor_power_mode(
+    state: tauri::State<'_, MonitorManager>,
+    monitor_id: MonitorId,
+) -> Result<PowerMode, MonitorError> {
+    state
+        .request(|reply| MonitorManagerCommand::GetPowerMode((monitor_id, reply)))
+        .await
+}
+
+#[tauri::command]
+async fn set_monitor_power_mode(
+    state: tauri::State<'_, MonitorManager>,
+    monitor_id: MonitorId,
+    mode: PowerMode,
+) -> Result<(), MonitorError> {
+    state
+        .request(|reply| MonitorManagerCommand::SetPowerMode((monitor_id, mode, reply)))
+        .await
+}
+
+#[tauri::command]
+async fn get_monitor_mute(
+    state: tauri::State<'_, MonitorManager>,
+    monitor_id: MonitorId,
+) -> Result<bool, MonitorError> {
+    state
+        .request(|reply| MonitorManagerCommand::GetMute((monitor_id, reply)))
+        .await
+}
+
+#[tauri::command]
+async fn set_monitor_mute(
+    state: tauri::State<'_, MonitorManager>,
+    monitor_id: MonitorId,
+    muted: bool,
+) -> Result<(), MonitorError> {
+    state
+        .request(|reply| MonitorManagerCommand::SetMute((monitor_id, muted, reply)))
+        .await
+}
+
+#[tauri::command]
+fn get_wake_on_switch(settings: tauri::State<'_, SettingsManager>) -> Result<bool, String> {
+    settings.read(|s| s.wake_on_switch)
+}
+
+/// Whether switching input first turns on monitors in standby
+#[tauri::command]
+fn set_wake_on_switch(
+    state: tauri::State<'_, MonitorManager>,
+    settings: tauri::State<'_, SettingsManager>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings.update(|s| {
+        s.wake_on_switch = enabled;
+        Ok(())
+    })?;
+
     state
         .tx
-        .send(MonitorManagerCommand::SwitchInput((monitor_idx, input)))
-        .ok();
+        .send(MonitorManagerCommand::SetWakeOnSwitch(enabled))
+        .map_err(|_| "Monitor manager stopped".to_string())
 }
 
-fn spawn_monitor_manager() -> SyncSender<MonitorManagerCommand> {
+#[tauri::command]
+fn get_profiles(settings: tauri::State<'_, SettingsManager>) -> Result<Vec<Profile>, String> {
+    settings.read(|s| s.profiles.clone())
+}
+
+#[tauri::command]
+fn create_profile(
+    settings: tauri::State<'_, SettingsManager>,
+    profile: Profile,
+) -> Result<(), String> {
+    settings.update(|s| s.create_profile(profile))
+}
+
+/// Replace a profile, shortcuts applying it follow when it's renamed. Async for
+/// the same reason as `set_shortcuts`.
+#[tauri::command]
+async fn edit_profile(
+    app: AppHandle,
+    settings: tauri::State<'_, SettingsManager>,
+    name: String,
+    profile: Profile,
+) -> Result<(), String> {
+    let renamed = profile.name != name;
+
+    let shortcuts = settings.update(|s| {
+        s.edit_profile(&name, profile)?;
+        Ok(s.shortcuts.clone())
+    })?;
+
+    // The registered shortcuts hold the name of the profile they apply
+    match renamed {
+        true => register_shortcuts(&app, &shortcuts).map_err(|failures| failures.join("\n")),
+        false => Ok(()),
+    }
+}
+
+/// Profiles that shortcuts still apply are refused
+#[tauri::command]
+fn delete_profile(settings: tauri::State<'_, SettingsManager>, name: String) -> Result<(), String> {
+    settings.update(|s| s.delete_profile(&name))
+}
+
+#[tauri::command]
+async fn apply_profile(
+    state: tauri::State<'_, MonitorManager>,
+    settings: tauri::State<'_, SettingsManager>,
+    name: String,
+) -> Result<Vec<StepResult>, MonitorError> {
+    apply_named_profile(&state, &settings, name).await
+}
+
+async fn apply_named_profile(
+    state: &MonitorManager,
+    settings: &SettingsManager,
+    name: String,
+) -> Result<Vec<StepResult>, MonitorError> {
+    let profile = settings
+        .read(|s| s.find_profile(&name).cloned())
+        .map_err(MonitorError::io)?
+        .ok_or(MonitorError::ProfileNotFound { name })?;
+
+    state
+        .request(|reply| MonitorManagerCommand::ApplyProfile((profile, reply)))
+        .await
+}
+
+#[tauri::command]
+fn get_shortcuts(settings: tauri::State<'_, SettingsManager>) -> Result<Vec<Shortcut>, String> {
+    settings.read(|s| s.shortcuts.clone())
+}
+
+/// Replaces every shortcut. Async so registering doesn't run on the main thread
+/// while it waits for the event loop. Shortcuts are saved even when some can't be
+/// registered, e.g. because another app holds the accelerator, and the failures
+/// are returned.
+#[tauri::command]
+async fn set_shortcuts(
+    app: AppHandle,
+    settings: tauri::State<'_, SettingsManager>,
+    shortcuts: Vec<Shortcut>,
+) -> Result<(), String> {
+    for shortcut in &shortcuts {
+        validate_accelerator(&shortcut.accelerator)?;
+    }
+
+    settings.update(|s| {
+        s.shortcuts = shortcuts.clone();
+        Ok(())
+    })?;
+
+    register_shortcuts(&app, &shortcuts).map_err(|failures| failures.join("\n"))
+}
+
+/// Register every shortcut that can be, a failing one doesn't keep the rest from
+/// working. Returns a message per failure.
+fn register_shortcuts(app: &AppHandle, shortcuts: &[Shortcut]) -> Result<(), Vec<String>> {
+    let mut manager = app.global_shortcut_manager();
+    let mut failures = vec![];
+
+    if let Err(e) = manager.unregister_all() {
+        failures.push(format!("Unable to unregister shortcuts: {}", e));
+    }
+
+    for shortcut in shortcuts {
+        let app = app.clone();
+        let action = shortcut.action.clone();
+
+        let result = validate_accelerator(&shortcut.accelerator).and_then(|_| {
+            manager
+                .register(&shortcut.accelerator, move || {
+                    run_shortcut_action(&app, &action)
+                })
+                .map_err(|e| format!("Unable to register {}: {}", shortcut.accelerator, e))
+        });
+
+        if let Err(e) = result {
+            failures.push(e);
+        }
+    }
+
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(failures),
+    }
+}
+
+fn run_shortcut_action(app: &AppHandle, action: &ShortcutAction) {
+    let action = action.clone();
+    let app = app.clone();
+
+    // Shortcuts fire on the main thread, wait for the result elsewhere
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<MonitorManager>();
+
+        let result = match action {
+            ShortcutAction::SwitchInput { monitor, input } => state
+                .request(|reply| MonitorManagerCommand::SwitchInput((monitor, input, reply)))
+                .await
+                .map(|_| ()),
+            ShortcutAction::CycleInput { monitor } => state
+                .request(|reply| MonitorManagerCommand::CycleInput((monitor, reply)))
+                .await
+                .map(|_| ()),
+            ShortcutAction::ApplyProfile { profile } => {
+                let settings = app.state::<SettingsManager>();
+
+                // Nobody looks at the results, report the first failed step instead
+                apply_named_profile(&state, &settings, profile)
+                    .await
+                    .and_then(|results| match results.into_iter().find_map(|r| r.error) {
+                        Some(e) => Err(e),
+                        None => Ok(()),
+                    })
+            }
+        };
+
+        if let Err(e) = result {
+            app.emit_all("monitor-error", e).ok();
+        }
+    });
+}
+
+/// Replace the cached list, returning the errors of the backends that failed
+fn enumerate(
+    monitors: &mut Vec<EnumeratedDisplay>,
+    registry: &BackendRegistry,
+) -> Vec<MonitorError> {
+    let enumeration = registry.enumerate();
+    *monitors = enumeration.displays;
+
+    enumeration.errors.into_iter().map(|(_, e)| e).collect()
+}
+
+/// Commands can arrive from shortcuts before the frontend asked for the list. Fails
+/// with why a backend couldn't enumerate when no monitor was found.
+fn ensure_enumerated(
+    monitors: &mut Vec<EnumeratedDisplay>,
+    registry: &BackendRegistry,
+) -> Result<(), MonitorError> {
+    if !monitors.is_empty() {
+        return Ok(());
+    }
+
+    match enumerate(monitors, registry).into_iter().next() {
+        Some(e) if monitors.is_empty() => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Look up a monitor in the cached list, enumerating again in case it was
+/// plugged in since the last refresh. A missing monitor is reported with the
+/// error of a failing backend when there is one, it may be behind that backend.
+fn find_monitor<'a>(
+    monitors: &'a mut Vec<EnumeratedDisplay>,
+    registry: &BackendRegistry,
+    id: &MonitorId,
+) -> Result<&'a EnumeratedDisplay, MonitorError> {
+    let mut errors = vec![];
+
+    if !monitors.iter().any(|m| m.display.id() == id) {
+        errors = enumerate(monitors, registry);
+    }
+
+    monitors
+        .iter()
+        .find(|m| m.display.id() == id)
+        .ok_or_else(|| {
+            errors
+                .into_iter()
+                .next()
+                .unwrap_or(MonitorError::MonitorNotFound { id: id.clone() })
+        })
+}
+
+fn cycle_input(monitor: &EnumeratedDisplay, wake: bool) -> Result<MonitorInput, MonitorError> {
+    let inputs = monitor.display.get_inputs()?;
+    let current = monitor.display.get_current_input().ok();
+
+    let next = current
+        .and_then(|current| inputs.iter().position(|i| *i == current))
+        .map(|position| (position + 1) % inputs.len())
+        .and_then(|position| inputs.get(position))
+        .or(inputs.first())
+        .copied()
+        .ok_or(MonitorError::UnsupportedFeature { code: 0x60 })?;
+
+    switch_input(monitor.display.as_ref(), next, wake)?;
+
+    Ok(next)
+}
+
+/// Write to every monitor that supports a VCP feature, going on past failures.
+/// Returns the first error.
+fn for_each_supporting(
+    monitors: &[EnumeratedDisplay],
+    code: u8,
+    write: impl Fn(&EnumeratedDisplay) -> Result<(), MonitorError>,
+) -> Result<(), MonitorError> {
+    let mut result = Ok(());
+
+    for monitor in monitors {
+        if monitor.display.check_vcp_feature(code, true, false).is_ok() {
+            result = result.and(write(monitor));
+        }
+    }
+
+    result
+}
+
+/// Dragging a slider queues an adjustment per step, which would take seconds to
+/// write one by one. Fold the queued adjustments of the same control that directly
+/// follow into one write, answering all of them with its result. Queued
+/// adjustments older than the ones before them are dropped.
+fn coalesce_adjustments(
+    pending: &mut VecDeque<MonitorManagerCommand>,
+    id: &MonitorId,
+    control: Control,
+    mut adjustment: Adjustment,
+    mut sequence: u64,
+    reply: Reply<u16>,
+) -> (Adjustment, u64, Vec<Reply<u16>>) {
+    let mut replies = vec![reply];
+
+    loop {
+        let merged = match pending.front() {
+            Some(MonitorManagerCommand::AdjustControl((
+                next_id,
+                next_control,
+                next,
+                next_sequence,
+                _,
+            ))) if next_id == id && *next_control == control => {
+                if *next_sequence < sequence {
+                    Some((adjustment, sequence))
+                } else {
+                    adjustment
+                        .then(*next)
+                        .map(|merged| (merged, *next_sequence))
+                }
+            }
+            _ => None,
+        };
+
+        let merged = match merged {
+            Some(merged) => merged,
+            None => break,
+        };
+
+        if let Some(MonitorManagerCommand::AdjustControl((_, _, _, _, reply))) = pending.pop_front()
+        {
+            replies.push(reply);
+        }
+
+        adjustment = merged.0;
+        sequence = merged.1;
+    }
+
+    (adjustment, sequence, replies)
+}
+
+fn spawn_monitor_manager(
+    registry: BackendRegistry,
+    mut wake_on_switch: bool,
+) -> SyncSender<MonitorManagerCommand> {
     let (tx, rx): (
         SyncSender<MonitorManagerCommand>,
         Receiver<MonitorManagerCommand>,
@@ -63,38 +632,135 @@ fn spawn_monitor_manager() -> SyncSender<MonitorManagerCommand> {
 
     thread::spawn(move || {
         let mut monitors = vec![];
+        let mut pending = VecDeque::new();
+        // The sequence number and result of the latest adjustment of each control
+        let mut adjusted: HashMap<(MonitorId, Control), (u64, Result<u16, MonitorError>)> =
+            HashMap::new();
 
-        for event in rx {
+        while let Some(event) = pending.pop_front().or_else(|| rx.recv().ok()) {
             match event {
                 MonitorManagerCommand::RefreshList(window) => {
-                    monitors = Monitor::get_all_monitors().map_or(vec![], |v| v);
-                    let info_list = monitors
-                        .iter()
-                        .map(|m| {
-                            let mut display_model = m
-                                .capabilities
-                                .as_ref()
-                                .map(|c| c.clone().display_model)
-                                .unwrap_or("Generic Display".to_string());
-
-                            if display_model == "" {
-                                display_model = "Generic Display".to_string();
-                            }
-
-                            MonitorInfo {
-                                id: m.id,
-                                model: display_model,
-                                inputs: m.get_inputs().unwrap_or(vec![]),
-                            }
-                        })
-                        .collect::<Vec<_>>();
+                    // A reloaded window numbers its adjustments from the start again
+                    adjusted.clear();
+
+                    let errors = enumerate(&mut monitors, &registry);
+                    let info_list = monitors.iter().map(MonitorInfo::from).collect::<Vec<_>>();
                     window.emit("monitor-info", info_list).unwrap();
+
+                    for e in errors {
+                        window.emit("monitor-error", e).ok();
+                    }
+                }
+                MonitorManagerCommand::SwitchInput((id, input, reply)) => {
+                    let result = find_monitor(&mut monitors, &registry, &id).and_then(|monitor| {
+                        switch_input(monitor.display.as_ref(), input, wake_on_switch)
+                    });
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::ApplyProfile((profile, reply)) => {
+                    let result = ensure_enumerated(&mut monitors, &registry)
+                        .map(|_| profile.apply(&monitors, wake_on_switch));
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::CycleInput((id, reply)) => {
+                    let result = find_monitor(&mut monitors, &registry, &id)
+                        .and_then(|monitor| cycle_input(monitor, wake_on_switch));
+
+                    reply.send(result).ok();
                 }
-                MonitorManagerCommand::SwitchInput((id, input)) => {
-                    if let Some(monitor) = monitors.iter().find(|m| m.id == id) {
-                        monitor.set_input(input).ok();
+                MonitorManagerCommand::GetVcp((id, code, force, reply)) => {
+                    let result = find_monitor(&mut monitors, &registry, &id)
+                        .and_then(|monitor| monitor.display.get_vcp(code, force));
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::SetVcp((id, code, value, force, reply)) => {
+                    let result = find_monitor(&mut monitors, &registry, &id)
+                        .and_then(|monitor| monitor.display.set_vcp(code, value, force));
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::AdjustControl((
+                    id,
+                    control,
+                    adjustment,
+                    sequence,
+                    reply,
+                )) => {
+                    pending.extend(rx.try_iter());
+
+                    let (adjustment, sequence, replies) = coalesce_adjustments(
+                        &mut pending,
+                        &id,
+                        control,
+                        adjustment,
+                        sequence,
+                        reply,
+                    );
+
+                    let result = match adjusted.get(&(id.clone(), control)) {
+                        // Overtaken by a later adjustment, whose value has to stay
+                        Some((latest, result)) if *latest > sequence => result.clone(),
+                        _ => {
+                            let result =
+                                find_monitor(&mut monitors, &registry, &id).and_then(|monitor| {
+                                    adjust_control(monitor.display.as_ref(), control, adjustment)
+                                });
+
+                            adjusted.insert((id, control), (sequence, result.clone()));
+                            result
+                        }
+                    };
+
+                    for reply in replies {
+                        reply.send(result.clone()).ok();
                     }
                 }
+                MonitorManagerCommand::GetPowerMode((id, reply)) => {
+                    let result = find_monitor(&mut monitors, &registry, &id)
+                        .and_then(|monitor| monitor.display.get_power_mode());
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::SetPowerMode((id, mode, reply)) => {
+                    let result = find_monitor(&mut monitors, &registry, &id)
+                        .and_then(|monitor| monitor.display.set_power_mode(mode));
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::SetAllPowerModes((mode, reply)) => {
+                    let result = ensure_enumerated(&mut monitors, &registry).and_then(|_| {
+                        for_each_supporting(&monitors, 0xD6, |monitor| {
+                            monitor.display.set_power_mode(mode)
+                        })
+                    });
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::GetMute((id, reply)) => {
+                    let result = find_monitor(&mut monitors, &registry, &id)
+                        .and_then(|monitor| get_mute(monitor.display.as_ref()));
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::SetMute((id, muted, reply)) => {
+                    let result = find_monitor(&mut monitors, &registry, &id)
+                        .and_then(|monitor| set_mute(monitor.display.as_ref(), muted));
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::SetAllMutes((muted, reply)) => {
+                    let result = ensure_enumerated(&mut monitors, &registry).and_then(|_| {
+                        for_each_supporting(&monitors, 0x8D, |monitor| {
+                            set_mute(monitor.display.as_ref(), muted)
+                        })
+                    });
+
+                    reply.send(result).ok();
+                }
+                MonitorManagerCommand::SetWakeOnSwitch(enabled) => wake_on_switch = enabled,
             }
         }
     });
@@ -102,8 +768,32 @@ fn spawn_monitor_manager() -> SyncSender<MonitorManagerCommand> {
     tx
 }
 
+/// Tray clicks arrive on the main thread, report failures as an event instead
+fn request_in_background(
+    app: &AppHandle,
+    command: impl FnOnce(Reply<()>) -> MonitorManagerCommand + Send + 'static,
+) {
+    let app = app.clone();
+
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<MonitorManager>();
+
+        let result = state.request(command).await;
+
+        if let Err(e) = result {
+            app.emit_all("monitor-error", e).ok();
+        }
+    });
+}
+
 fn make_tray() -> SystemTray {
     let menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new("power_off", "Turn all monitors off"))
+        .add_item(CustomMenuItem::new("power_on", "Turn all monitors on"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("mute", "Mute all monitors"))
+        .add_item(CustomMenuItem::new("unmute", "Unmute all monitors"))
+        .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(CustomMenuItem::new("refresh", "Refresh"))
         .add_item(CustomMenuItem::new("quit", "Quit"));
 
@@ -123,6 +813,18 @@ fn handle_tray_event(app: &AppHandle, event: SystemTrayEvent) {
                         .ok();
                 }
             }
+            "power_off" => request_in_background(app, |reply| {
+                MonitorManagerCommand::SetAllPowerModes((PowerMode::Off, reply))
+            }),
+            "power_on" => request_in_background(app, |reply| {
+                MonitorManagerCommand::SetAllPowerModes((PowerMode::On, reply))
+            }),
+            "mute" => request_in_background(app, |reply| {
+                MonitorManagerCommand::SetAllMutes((true, reply))
+            }),
+            "unmute" => request_in_background(app, |reply| {
+                MonitorManagerCommand::SetAllMutes((false, reply))
+            }),
             "quit" => app.exit(0),
             _ => (),
         },
@@ -137,12 +839,37 @@ fn handle_tray_event(app: &AppHandle, event: SystemTrayEvent) {
 
 fn main() {
     let app = tauri::Builder::default()
-        .manage(MonitorManager {
-            tx: spawn_monitor_manager(),
-        })
         .system_tray(make_tray())
         .on_system_tray_event(handle_tray_event)
         .setup(|app| {
+            let app_dir = app.path_resolver().app_dir().unwrap_or_default();
+
+            let errors = StartupErrors::default();
+
+            let mut registry = BackendRegistry::from_env();
+
+            for e in registry.quirks_mut().load_user_files() {
+                errors.push(e.to_string());
+            }
+
+            let settings = SettingsManager::load(app_dir.join("settings.json"), &errors);
+            let shortcuts = settings.read(|s| s.shortcuts.clone()).unwrap_or_default();
+            let wake_on_switch = settings.read(|s| s.wake_on_switch).unwrap_or_default();
+
+            app.manage(MonitorManager {
+                tx: spawn_monitor_manager(registry, wake_on_switch),
+            });
+
+            app.manage(settings);
+
+            if let Err(failures) = register_shortcuts(&app.handle(), &shortcuts) {
+                failures
+                    .into_iter()
+                    .for_each(|failure| errors.push(failure));
+            }
+
+            app.manage(errors);
+
             #[cfg(debug_assertions)]
             {
                 let window = app.get_window("main").unwrap();
@@ -153,25 +880,159 @@ fn main() {
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            take_startup_errors,
             refresh_monitor_info,
-            switch_monitor_input
+            switch_monitor_input,
+            get_vcp_feature,
+            set_vcp_feature,
+            get_monitor_control,
+            adjust_monitor_control,
+            get_monitor_power_mode,
+            set_monitor_power_mode,
+            get_monitor_mute,
+            set_monitor_mute,
+            get_wake_on_switch,
+            set_wake_on_switch,
+            get_profiles,
+            create_profile,
+            edit_profile,
+            delete_profile,
+            apply_profile,
+            get_shortcuts,
+            set_shortcuts
         ])
         .build(tauri::generate_context!())
         .expect("error while running tauri application");
 
-    app.run(|app_handle, e| match e {
-        RunEvent::WindowEvent { event, .. } => match event {
-            tauri::WindowEvent::CloseRequested { api, .. } => {
-                let app_handle = app_handle.clone();
+    app.run(|app_handle, e| {
+        if let RunEvent::WindowEvent {
+            event: tauri::WindowEvent::CloseRequested { api, .. },
+            ..
+        } = e
+        {
+            let app_handle = app_handle.clone();
 
-                api.prevent_close();
+            api.prevent_close();
 
-                if let Some(window) = app_handle.get_window("main") {
-                    window.hide().ok();
-                }
+            if let Some(window) = app_handle.get_window("main") {
+                window.hide().ok();
             }
-            _ => (),
-        },
-        _ => (),
+        }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Adjustment::{Absolute, Relative};
+
+    /// Queue adjustments of `(monitor, control, adjustment, sequence)`, keeping
+    /// their reply receivers alive
+    fn queue(
+        adjustments: &[(&str, Control, Adjustment, u64)],
+    ) -> (
+        VecDeque<MonitorManagerCommand>,
+        Vec<Receiver<Result<u16, MonitorError>>>,
+    ) {
+        let mut pending = VecDeque::new();
+        let mut receivers = vec![];
+
+        for (monitor, control, adjustment, sequence) in adjustments {
+            let (reply, rx) = mpsc::channel();
+            let id = MonitorId::from_bus_path(monitor);
+
+            pending.push_back(MonitorManagerCommand::AdjustControl((
+                id,
+                *control,
+                *adjustment,
+                *sequence,
+                reply,
+            )));
+            receivers.push(rx);
+        }
+
+        (pending, receivers)
+    }
+
+    fn coalesce(
+        pending: &mut VecDeque<MonitorManagerCommand>,
+        adjustment: Adjustment,
+        sequence: u64,
+    ) -> (Adjustment, u64, usize) {
+        let (reply, _rx) = mpsc::channel();
+        let id = MonitorId::from_bus_path("a");
+
+        let (adjustment, sequence, replies) = coalesce_adjustments(
+            pending,
+            &id,
+            Control::Brightness,
+            adjustment,
+            sequence,
+            reply,
+        );
+
+        (adjustment, sequence, replies.len())
+    }
+
+    #[test]
+    fn merges_queued_adjustments_of_the_same_control() {
+        let (mut pending, _receivers) = queue(&[
+            ("a", Control::Brightness, Absolute(20), 2),
+            ("a", Control::Brightness, Absolute(30), 3),
+            ("a", Control::Contrast, Absolute(40), 4),
+            ("a", Control::Brightness, Absolute(50), 5),
+        ]);
+
+        assert_eq!(
+            coalesce(&mut pending, Absolute(10), 1),
+            (Absolute(30), 3, 3)
+        );
+        assert_eq!(pending.len(), 2);
+
+        let (mut pending, _receivers) = queue(&[
+            ("a", Control::Brightness, Relative(2), 2),
+            ("a", Control::Brightness, Relative(-5), 3),
+        ]);
+
+        assert_eq!(coalesce(&mut pending, Relative(1), 1), (Relative(-2), 3, 3));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn stops_at_other_monitors_and_unmergeable_adjustments() {
+        let (mut pending, _receivers) = queue(&[
+            ("b", Control::Brightness, Absolute(20), 2),
+            ("a", Control::Brightness, Absolute(30), 3),
+        ]);
+
+        assert_eq!(
+            coalesce(&mut pending, Absolute(10), 1),
+            (Absolute(10), 1, 1)
+        );
+        assert_eq!(pending.len(), 2);
+
+        let (mut pending, _receivers) = queue(&[("a", Control::Brightness, Relative(5), 2)]);
+
+        assert_eq!(
+            coalesce(&mut pending, Absolute(10), 1),
+            (Absolute(10), 1, 1)
+        );
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[test]
+    fn drops_overtaken_adjustments() {
+        let (mut pending, _receivers) = queue(&[
+            ("a", Control::Brightness, Absolute(20), 2),
+            ("a", Control::Brightness, Absolute(40), 4),
+            ("a", Control::Brightness, Absolute(35), 3),
+        ]);
+
+        assert_eq!(
+            coalesce(&mut pending, Absolute(30), 3),
+            (Absolute(40), 4, 4)
+        );
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,366 @@
+use crate::{
+    errors::MonitorError,
+    monitor::{
+        capabilities::MonitorCapabilities,
+        edid::Edid,
+        emulator::EmulatorBackend,
+        features::{self, FeatureKind},
+        identity::MonitorId,
+        input::MonitorInput,
+        power::PowerMode,
+        quirks::QuirkDatabase,
+        Monitor,
+    },
+};
+
+/// DDC/CI source address of the host, used for every write unless a monitor needs
+/// another one.
+pub const HOST_SOURCE_ADDRESS: u8 = 0x51;
+
+/// The value of a VCP feature and how to interpret it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct VcpValue {
+    pub current: u16,
+    pub maximum: u16,
+    pub kind: FeatureKind,
+}
+
+/// The kind MCCS defines for a feature. Manufacturer features are taken as
+/// non-continuous when the capability string lists their values, and as
+/// continuous otherwise.
+fn feature_kind(capabilities: Option<&MonitorCapabilities>, code: u8) -> FeatureKind {
+    if let Some(feature) = features::lookup(code) {
+        return feature.kind;
+    }
+
+    match capabilities.and_then(|c| c.vcp_feature(code)) {
+        Some(cmd) if !cmd.values.is_empty() => FeatureKind::NonContinuous,
+        _ => FeatureKind::Continuous,
+    }
+}
+
+/// A single display that can be controlled through one of the registered backends.
+pub trait ControlledDisplay {
+    fn id(&self) -> &MonitorId;
+
+    fn capabilities(&self) -> Option<&MonitorCapabilities>;
+
+    /// Why the capabilities couldn't be read, when they couldn't
+    fn capabilities_error(&self) -> Option<&MonitorError>;
+
+    fn edid(&self) -> Option<&Edid>;
+
+    fn get_inputs(&self) -> Result<Vec<MonitorInput>, MonitorError>;
+
+    /// Returns the current and maximum value of a VCP feature
+    fn get_vcp_feature(&self, code: u8) -> Result<(u16, u16), MonitorError>;
+
+    fn set_vcp_feature(&self, code: u8, value: u16) -> Result<(), MonitorError>;
+
+    /// Write a VCP feature with a different DDC/CI source address. Some vendor
+    /// features only react to writes that don't come from the standard host address.
+    fn set_vcp_feature_from(
+        &self,
+        source_address: u8,
+        code: u8,
+        value: u16,
+    ) -> Result<(), MonitorError> {
+        match source_address {
+            HOST_SOURCE_ADDRESS => self.set_vcp_feature(code, value),
+            address => Err(MonitorError::UnsupportedSourceAddress { address }),
+        }
+    }
+
+    /// Fail for features the capability string doesn't list, table features, and
+    /// writes to features MCCS defines as read-only, unless forced. Monitors whose
+    /// capabilities couldn't be read are assumed to list every feature.
+    fn check_vcp_feature(&self, code: u8, write: bool, force: bool) -> Result<(), MonitorError> {
+        if force {
+            return Ok(());
+        }
+
+        let unsupported = match features::lookup(code) {
+            Some(feature) => {
+                feature.kind == FeatureKind::Table
+                    || (write && !feature.is_writable())
+                    || (!write && !feature.is_readable())
+            }
+            None => false,
+        };
+        let unlisted = matches!(
+            self.capabilities(),
+            Some(capabilities) if capabilities.vcp_feature(code).is_none()
+        );
+
+        match unsupported || unlisted {
+            true => Err(MonitorError::UnsupportedFeature { code }),
+            false => Ok(()),
+        }
+    }
+
+    fn get_vcp(&self, code: u8, force: bool) -> Result<VcpValue, MonitorError> {
+        self.check_vcp_feature(code, false, force)?;
+
+        let (current, maximum) = self.get_vcp_feature(code)?;
+
+        Ok(VcpValue {
+            current,
+            maximum,
+            kind: feature_kind(self.capabilities(), code),
+        })
+    }
+
+    fn set_vcp(&self, code: u8, value: u16, force: bool) -> Result<(), MonitorError> {
+        self.check_vcp_feature(code, true, force)?;
+        self.set_vcp_feature(code, value)
+    }
+
+    fn get_current_input(&self) -> Result<MonitorInput, MonitorError> {
+        let (value, _) = self.get_vcp_feature(0x60)?;
+
+        // Some monitors report garbage in the high byte, input codes only use the low byte
+        Ok(MonitorInput::from((value & 0xff) as u8))
+    }
+
+    fn set_input(&self, input: MonitorInput) -> Result<(), MonitorError> {
+        self.set_vcp_feature(0x60, input.code() as u16)
+    }
+
+    fn get_power_mode(&self) -> Result<PowerMode, MonitorError> {
+        let (value, _) = self.get_vcp_feature(0xD6)?;
+
+        PowerMode::from_code((value & 0xff) as u8).ok_or(MonitorError::Protocol {
+            message: format!("unknown power mode 0x{:02X}", value),
+        })
+    }
+
+    fn set_power_mode(&self, mode: PowerMode) -> Result<(), MonitorError> {
+        self.set_vcp_feature(0xD6, mode.code() as u16)
+    }
+}
+
+/// A source of controllable displays, e.g. DDC/CI over the graphics card's I2C bus.
+pub trait DisplayBackend: Send {
+    fn name(&self) -> &'static str;
+
+    fn enumerate(&self) -> Result<Vec<Box<dyn ControlledDisplay>>, MonitorError>;
+}
+
+/// Talks to the monitors attached to this machine over DDC/CI.
+pub struct DdcBackend;
+
+impl DisplayBackend for DdcBackend {
+    fn name(&self) -> &'static str {
+        "ddc"
+    }
+
+    fn enumerate(&self) -> Result<Vec<Box<dyn ControlledDisplay>>, MonitorError> {
+        Ok(Monitor::get_all_monitors()?
+            .into_iter()
+            .map(|m| Box::new(m) as Box<dyn ControlledDisplay>)
+            .collect())
+    }
+}
+
+pub struct EnumeratedDisplay {
+    pub backend: &'static str,
+    pub display: Box<dyn ControlledDisplay>,
+}
+
+/// The displays every backend found, and why the others failed to enumerate
+#[derive(Default)]
+pub struct Enumeration {
+    pub displays: Vec<EnumeratedDisplay>,
+    /// The name of each failing backend, with its error
+    pub errors: Vec<(&'static str, MonitorError)>,
+}
+
+#[derive(Default)]
+pub struct BackendRegistry {
+    backends: Vec<Box<dyn DisplayBackend>>,
+    quirks: QuirkDatabase,
+}
+
+impl BackendRegistry {
+    /// Demo mode swaps the real monitors for emulated ones. It is enabled with `--demo`
+    /// or the `SHORTMON_DEMO` env var, which may point to a JSON file of monitor
+    /// descriptions to use instead of the built-in ones.
+    ///
+    /// Only the bundled quirks are loaded, the user's are added with
+    /// `QuirkDatabase::load_user_files`.
+    pub fn from_env() -> BackendRegistry {
+        let mut registry = BackendRegistry {
+            quirks: QuirkDatabase::bundled(),
+            ..Default::default()
+        };
+
+        let demo_env = std::env::var("SHORTMON_DEMO").ok();
+        let demo = std::env::args().any(|arg| arg == "--demo") || demo_env.is_some();
+
+        if !demo {
+            registry.register(DdcBackend);
+            return registry;
+        }
+
+        let emulator = match demo_env.as_deref() {
+            Some(path) if !matches!(path, "" | "1" | "true") => {
+                EmulatorBackend::from_file(path.as_ref()).unwrap_or_else(|e| {
+                    eprintln!("Unable to load demo monitors from {}: {}", path, e);
+                    EmulatorBackend::demo()
+                })
+            }
+            _ => EmulatorBackend::demo(),
+        };
+
+        registry.register(emulator);
+
+        registry
+    }
+
+    pub fn register(&mut self, backend: impl DisplayBackend + 'static) {
+        self.backends.push(Box::new(backend));
+    }
+
+    pub fn quirks_mut(&mut self) -> &mut QuirkDatabase {
+        &mut self.quirks
+    }
+
+    /// Enumerate the displays of every backend in registration order. A backend
+    /// failing to enumerate doesn't hide the displays of the others, and a display
+    /// reachable through more than one backend is only listed by the first one.
+    pub fn enumerate(&self) -> Enumeration {
+        let mut enumeration = Enumeration::default();
+
+        for backend in &self.backends {
+            let displays = match backend.enumerate() {
+                Ok(displays) => displays,
+                Err(e) => {
+                    enumeration.errors.push((backend.name(), e));
+                    continue;
+                }
+            };
+
+            for display in displays {
+                if enumeration
+                    .displays
+                    .iter()
+                    .any(|d| d.display.id() == display.id())
+                {
+                    continue;
+                }
+
+                enumeration.displays.push(EnumeratedDisplay {
+                    backend: backend.name(),
+                    display: self.quirks.apply(display),
+                });
+            }
+        }
+
+        enumeration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::emulator::EmulatedMonitorDescription;
+
+    fn display() -> Box<dyn ControlledDisplay> {
+        let description = EmulatedMonitorDescription::test(
+            "TEST",
+            "(prot(monitor)model(TEST)vcp(10 73 AC)mccs_ver(2.1))",
+        )
+        .feature(0x10, 50, 100)
+        .feature(0x73, 0, 0)
+        .feature(0xAC, 60, 0);
+        let backend = EmulatorBackend::new(vec![description]);
+
+        backend.enumerate().unwrap().remove(0)
+    }
+
+    #[test]
+    fn accepts_listed_features() {
+        let display = display();
+
+        display.set_vcp(0x10, 20, false).unwrap();
+        assert_eq!(
+            display.get_vcp(0x10, false),
+            Ok(VcpValue {
+                current: 20,
+                maximum: 100,
+                kind: FeatureKind::Continuous
+            })
+        );
+    }
+
+    #[test]
+    fn refuses_unlisted_features_unless_forced() {
+        let display = display();
+        let unsupported = Err(MonitorError::UnsupportedFeature { code: 0x12 });
+
+        assert_eq!(display.get_vcp(0x12, false).map(|_| ()), unsupported);
+        assert_eq!(display.set_vcp(0x12, 20, false), unsupported);
+    }
+
+    #[test]
+    fn refuses_table_features_unless_forced() {
+        let display = display();
+
+        assert_eq!(
+            display.get_vcp(0x73, false),
+            Err(MonitorError::UnsupportedFeature { code: 0x73 })
+        );
+        assert_eq!(
+            display.set_vcp(0x73, 1, false),
+            Err(MonitorError::UnsupportedFeature { code: 0x73 })
+        );
+        assert!(display.get_vcp(0x73, true).is_ok());
+    }
+
+    #[test]
+    fn refuses_writes_to_read_only_features_unless_forced() {
+        let display = display();
+
+        assert_eq!(display.get_vcp(0xAC, false).map(|v| v.current), Ok(60));
+        assert_eq!(
+            display.set_vcp(0xAC, 1, false),
+            Err(MonitorError::UnsupportedFeature { code: 0xAC })
+        );
+        assert!(display.check_vcp_feature(0xAC, true, true).is_ok());
+        display.set_vcp(0xAC, 1, true).unwrap();
+    }
+
+    struct FailingBackend;
+
+    impl DisplayBackend for FailingBackend {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn enumerate(&self) -> Result<Vec<Box<dyn ControlledDisplay>>, MonitorError> {
+            Err(MonitorError::PermissionDenied {
+                message: "/dev/i2c-1".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn collects_backend_errors() {
+        let mut registry = BackendRegistry::default();
+        registry.register(FailingBackend);
+        registry.register(EmulatorBackend::demo());
+
+        let enumeration = registry.enumerate();
+
+        assert_eq!(enumeration.displays.len(), 3);
+        assert_eq!(
+            enumeration.errors,
+            [(
+                "failing",
+                MonitorError::PermissionDenied {
+                    message: "/dev/i2c-1".to_string()
+                }
+            )]
+        );
+    }
+}
@@ -1,37 +1,421 @@
-use crate::monitor::mccs::{ParserError, VCPCommand};
+use crate::{
+    errors::MonitorError,
+    monitor::mccs::{ParseMode, ParserError, VCPCommand},
+};
 
-use super::mccs::{extract_atom, extract_vcp_commands, parse_cap_string};
+use super::mccs::{
+    extract_atom, extract_vcp_commands, format_code, format_value, parse_cap_string_with,
+    parse_code, skip_invalid, to_cap_string, Expression,
+};
 
-#[derive(Default, Debug, Clone)]
+/// Manufacturer defined name of a VCP feature from the `vcpname` section, e.g.
+/// `vcpname(E0(Hot Key) 60(Input(DP HDMI USB-C)))`. Value names are in the order
+/// the values are listed in the `vcp` section.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct VcpName {
+    pub code: u8,
+    pub name: String,
+    pub value_names: Vec<String>,
+}
+
+/// A window (e.g. picture in picture) from the `window` section
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct WindowCapabilities {
+    pub name: String,
+    pub window_type: String,
+    /// Left, top, right and bottom edge of the area the window can be placed in
+    pub area: Option<(u16, u16, u16, u16)>,
+    pub max_size: Option<(u16, u16)>,
+    pub min_size: Option<(u16, u16)>,
+}
+
+/// Audio features a monitor with speakers or a headphone jack lists
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct AudioFeatures {
+    /// VCP 0x62
+    pub volume: bool,
+    /// VCP 0x8D
+    pub mute: bool,
+    /// VCP 0x8F
+    pub treble: bool,
+    /// VCP 0x91
+    pub bass: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct MonitorCapabilities {
     pub protocol_class: String,
     pub display_type: String,
-    pub commands: Vec<VCPCommand>,
+    /// DDC/CI command opcodes the monitor supports
+    pub commands: Vec<u8>,
     pub vcp_codes: Vec<VCPCommand>,
     pub display_model: String,
     pub mccs_version: String,
+    pub vcp_names: Vec<VcpName>,
+    /// Whether the monitor is Microsoft WHQL certified
+    pub mswhql: Option<bool>,
+    /// Asset management EEPROM tag, vendor specific
+    pub asset_eep: Option<String>,
+    /// Firmware (microprocessor unit) version
+    pub mpu_version: Option<String>,
+    pub windows: Vec<WindowCapabilities>,
+    /// Malformations repaired while parsing leniently
+    pub warnings: Vec<String>,
 }
 
 impl MonitorCapabilities {
     pub fn from_cap_string(cap_string: String) -> Result<MonitorCapabilities, ParserError> {
-        let pairs = parse_cap_string(cap_string)?;
+        MonitorCapabilities::from_cap_string_with(cap_string, ParseMode::Strict)
+    }
+
+    pub fn from_cap_string_with(
+        cap_string: String,
+        mode: ParseMode,
+    ) -> Result<MonitorCapabilities, ParserError> {
+        let mut warnings = vec![];
+        let pairs = parse_cap_string_with(cap_string.clone(), mode, &mut warnings)?;
 
         let mut caps = MonitorCapabilities {
+            warnings,
             ..Default::default()
         };
 
         for (key, value) in pairs {
-            match key.as_str() {
-                "prot" => caps.protocol_class = extract_atom(value),
-                "type" => caps.display_type = extract_atom(value),
-                "cmds" => caps.commands = extract_vcp_commands(value),
-                "vcp" => caps.vcp_codes = extract_vcp_commands(value),
-                "model" => caps.display_model = extract_atom(value),
-                "mccs_ver" => caps.mccs_version = extract_atom(value),
+            caps.extract(&key, value, mode)
+                .map_err(|e| e.within(&key).locate(&cap_string))?;
+        }
+
+        Ok(caps)
+    }
+
+    fn extract(
+        &mut self,
+        key: &str,
+        value: Expression,
+        mode: ParseMode,
+    ) -> Result<(), ParserError> {
+        match key {
+            "prot" => self.protocol_class = extract_atom(value),
+            "type" => self.display_type = extract_atom(value),
+            "cmds" => {
+                self.commands = extract_vcp_commands(value, mode, &mut self.warnings)?
+                    .into_iter()
+                    .map(|cmd| cmd.code)
+                    .collect()
+            }
+            "vcp" => self.vcp_codes = extract_vcp_commands(value, mode, &mut self.warnings)?,
+            "model" => self.display_model = extract_atom(value),
+            "mccs_ver" => self.mccs_version = extract_atom(value),
+            "vcpname" => self.vcp_names = extract_vcp_names(value, mode, &mut self.warnings)?,
+            "mswhql" => self.mswhql = Some(extract_atom(value) == "1"),
+            "asset_eep" => self.asset_eep = Some(extract_atom(value)),
+            "mpu" => self.mpu_version = Some(extract_atom(value)),
+            "window" => self.windows = extract_windows(value),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Write the capabilities as a canonical capability string, leaving out empty
+    /// fields. Parsing it again gives back the same capabilities, warnings aside.
+    pub fn to_cap_string(&self) -> String {
+        let mut pairs = vec![];
+
+        let text = |key: &str, value: &str| {
+            let list = match value.is_empty() {
+                true => vec![],
+                false => vec![Expression::atom(value)],
+            };
+
+            (key.to_string(), Expression::list(list))
+        };
+
+        if !self.protocol_class.is_empty() {
+            pairs.push(text("prot", &self.protocol_class));
+        }
+
+        if !self.display_type.is_empty() {
+            pairs.push(text("type", &self.display_type));
+        }
+
+        if !self.display_model.is_empty() {
+            pairs.push(text("model", &self.display_model));
+        }
+
+        if !self.commands.is_empty() {
+            let commands = self
+                .commands
+                .iter()
+                .map(|c| Expression::atom(&format_code(*c)));
+
+            pairs.push(("cmds".to_string(), Expression::list(commands.collect())));
+        }
+
+        if !self.vcp_codes.is_empty() {
+            let mut vcp = vec![];
+
+            for cmd in &self.vcp_codes {
+                vcp.push(Expression::atom(&format_code(cmd.code)));
+
+                if !cmd.values.is_empty() {
+                    let values = cmd
+                        .values
+                        .iter()
+                        .map(|v| Expression::atom(&format_value(*v)));
+                    vcp.push(Expression::list(values.collect()));
+                }
+            }
+
+            pairs.push(("vcp".to_string(), Expression::list(vcp)));
+        }
+
+        if !self.vcp_names.is_empty() {
+            let mut names = vec![];
+
+            for name in &self.vcp_names {
+                let text = match name.value_names.is_empty() {
+                    true => name.name.clone(),
+                    false => format!("{}({})", name.name, name.value_names.join(" ")),
+                };
+
+                names.push(Expression::atom(&format_code(name.code)));
+                names.push(Expression::list(vec![Expression::atom(&text)]));
+            }
+
+            pairs.push(("vcpname".to_string(), Expression::list(names)));
+        }
+
+        if !self.windows.is_empty() {
+            let windows = self.windows.iter().flat_map(|window| {
+                let mut properties = vec![];
+
+                if !window.window_type.is_empty() {
+                    properties.push(Expression::atom("type"));
+                    properties.push(Expression::list(vec![Expression::atom(
+                        &window.window_type,
+                    )]));
+                }
+
+                let sizes = [
+                    ("area", window.area.map(|(l, t, r, b)| vec![l, t, r, b])),
+                    ("max", window.max_size.map(|(w, h)| vec![w, h])),
+                    ("min", window.min_size.map(|(w, h)| vec![w, h])),
+                ];
+
+                for (key, numbers) in sizes {
+                    if let Some(numbers) = numbers {
+                        let numbers = numbers.iter().map(|n| Expression::atom(&n.to_string()));
+
+                        properties.push(Expression::atom(key));
+                        properties.push(Expression::list(numbers.collect()));
+                    }
+                }
+
+                [Expression::atom(&window.name), Expression::list(properties)]
+            });
+
+            pairs.push(("window".to_string(), Expression::list(windows.collect())));
+        }
+
+        if let Some(mswhql) = self.mswhql {
+            pairs.push(text("mswhql", if mswhql { "1" } else { "0" }));
+        }
+
+        if let Some(asset_eep) = &self.asset_eep {
+            pairs.push(text("asset_eep", asset_eep));
+        }
+
+        if let Some(mpu_version) = &self.mpu_version {
+            pairs.push(text("mpu", mpu_version));
+        }
+
+        if !self.mccs_version.is_empty() {
+            pairs.push(text("mccs_ver", &self.mccs_version));
+        }
+
+        to_cap_string(&pairs)
+    }
+
+    /// Parse the capability string as the monitor replied with it, NUL padding
+    /// included. Parsed leniently since plenty of monitors get the syntax wrong.
+    pub fn from_reply(reply: Vec<u8>) -> Result<MonitorCapabilities, MonitorError> {
+        let cap_string = String::from_utf8(reply)
+            .map_err(|e| MonitorError::capability_parse("string isn't valid UTF-8", e))?;
+        let cap_string = cap_string.trim_matches(char::from(0)).trim();
+
+        if cap_string.is_empty() {
+            return Err(MonitorError::capability_parse(
+                "no capability string",
+                "the monitor replied with an empty string",
+            ));
+        }
+
+        MonitorCapabilities::from_cap_string_with(cap_string.to_string(), ParseMode::Lenient)
+            .map_err(|e| MonitorError::capability_parse("invalid syntax", e))
+    }
+
+    /// The `vcp` entry for a feature, if the monitor lists it
+    pub fn vcp_feature(&self, code: u8) -> Option<&VCPCommand> {
+        self.vcp_codes.iter().find(|cmd| cmd.code == code)
+    }
+
+    /// Whether the monitor lists a VCP feature
+    pub fn supports(&self, code: u8) -> bool {
+        self.vcp_feature(code).is_some()
+    }
+
+    pub fn audio_features(&self) -> AudioFeatures {
+        AudioFeatures {
+            volume: self.supports(0x62),
+            mute: self.supports(0x8D),
+            treble: self.supports(0x8F),
+            bass: self.supports(0x91),
+        }
+    }
+
+    /// The manufacturer's name for a VCP feature, if the monitor gives one
+    pub fn feature_name(&self, code: u8) -> Option<&str> {
+        self.vcp_names
+            .iter()
+            .find(|n| n.code == code)
+            .map(|n| n.name.as_str())
+    }
+
+    /// The manufacturer's name for one of the values of a VCP feature
+    pub fn value_name(&self, code: u8, value: u16) -> Option<&str> {
+        let index = self
+            .vcp_feature(code)?
+            .values
+            .iter()
+            .position(|v| *v == value)?;
+
+        self.vcp_names
+            .iter()
+            .find(|n| n.code == code)?
+            .value_names
+            .get(index)
+            .map(|name| name.as_str())
+    }
+}
+
+/// Pairs of a code and its name text, where the text may end with the value
+/// names in parentheses
+fn extract_vcp_names(
+    expression: Expression,
+    mode: ParseMode,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<VcpName>, ParserError> {
+    let list = match expression {
+        Expression::List(list, _) => list,
+        _ => return Ok(vec![]),
+    };
+
+    let mut names = vec![];
+    let mut iter = list.into_iter().peekable();
+
+    while let Some(item) = iter.next() {
+        let (code, span) = match item {
+            Expression::Atom(code, span) => (code, span),
+            _ => continue,
+        };
+
+        let text = match iter.peek() {
+            Some(Expression::List(..)) => extract_atom(iter.next().unwrap()),
+            _ => continue,
+        };
+
+        let code = match parse_code(&code) {
+            Ok(code) => code,
+            Err(e) => {
+                skip_invalid(mode, warnings, e.spanned(span))?;
+                continue;
+            }
+        };
+
+        let (name, value_names) = match text.split_once('(') {
+            Some((name, values)) => (
+                name.trim().to_string(),
+                values
+                    .trim_end_matches(')')
+                    .split_whitespace()
+                    .map(|v| v.to_string())
+                    .collect(),
+            ),
+            None => (text.trim().to_string(), vec![]),
+        };
+
+        names.push(VcpName {
+            code,
+            name,
+            value_names,
+        });
+    }
+
+    Ok(names)
+}
+
+/// Each window is a name followed by `type(..) area(..) max(..) min(..)` properties
+fn extract_windows(expression: Expression) -> Vec<WindowCapabilities> {
+    let list = match expression {
+        Expression::List(list, _) => list,
+        _ => return vec![],
+    };
+
+    let mut windows = vec![];
+    let mut iter = list.into_iter().peekable();
+
+    while let Some(item) = iter.next() {
+        let name = match item {
+            Expression::Atom(name, _) => name,
+            _ => continue,
+        };
+
+        let properties = match iter.peek() {
+            Some(Expression::List(..)) => match iter.next() {
+                Some(Expression::List(properties, _)) => properties,
+                _ => unreachable!(),
+            },
+            _ => continue,
+        };
+
+        let mut window = WindowCapabilities {
+            name,
+            ..Default::default()
+        };
+
+        let mut properties = properties.into_iter();
+        while let (Some(Expression::Atom(key, _)), Some(value)) =
+            (properties.next(), properties.next())
+        {
+            let numbers = extract_numbers(&value);
+
+            match (key.as_str(), numbers.as_slice()) {
+                ("type", _) => window.window_type = extract_atom(value),
+                ("area", [left, top, right, bottom]) => {
+                    window.area = Some((*left, *top, *right, *bottom))
+                }
+                ("max", [width, height]) => window.max_size = Some((*width, *height)),
+                ("min", [width, height]) => window.min_size = Some((*width, *height)),
                 _ => {}
             }
         }
 
-        return Ok(caps);
+        windows.push(window);
+    }
+
+    windows
+}
+
+fn extract_numbers(expression: &Expression) -> Vec<u16> {
+    match expression {
+        Expression::List(list, _) => list
+            .iter()
+            .filter_map(|e| match e {
+                Expression::Atom(value, _) => value.parse().ok(),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
     }
 }
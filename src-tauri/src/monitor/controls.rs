@@ -0,0 +1,152 @@
+use crate::{
+    errors::MonitorError,
+    monitor::backend::{ControlledDisplay, VcpValue},
+};
+
+/// Continuous features adjusted with a slider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Control {
+    Brightness,
+    Contrast,
+    Volume,
+    Treble,
+    Bass,
+}
+
+impl Control {
+    pub fn code(&self) -> u8 {
+        match self {
+            Control::Brightness => 0x10,
+            Control::Contrast => 0x12,
+            Control::Volume => 0x62,
+            Control::Treble => 0x8F,
+            Control::Bass => 0x91,
+        }
+    }
+}
+
+/// A new value for a control, e.g. `{"absolute": 50}` or `{"relative": -10}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Adjustment {
+    /// Written as is, capped at the maximum
+    Absolute(u16),
+    /// Added to the current value, staying between 0 and the maximum
+    Relative(i32),
+}
+
+impl Adjustment {
+    /// One adjustment with the effect of this one followed by `later`, if there is
+    /// one. Used to send a burst of queued adjustments as a single write.
+    pub fn then(self, later: Adjustment) -> Option<Adjustment> {
+        match (self, later) {
+            (_, Adjustment::Absolute(value)) => Some(Adjustment::Absolute(value)),
+            (Adjustment::Relative(a), Adjustment::Relative(b)) => {
+                Some(Adjustment::Relative(a.saturating_add(b)))
+            }
+            // The maximum to stay under is only known once the monitor is read
+            (Adjustment::Absolute(_), Adjustment::Relative(_)) => None,
+        }
+    }
+}
+
+pub fn get_control(
+    display: &dyn ControlledDisplay,
+    control: Control,
+) -> Result<VcpValue, MonitorError> {
+    display.get_vcp(control.code(), false)
+}
+
+/// Apply an adjustment, clamped to the maximum the monitor reports, and return the
+/// value written
+pub fn adjust_control(
+    display: &dyn ControlledDisplay,
+    control: Control,
+    adjustment: Adjustment,
+) -> Result<u16, MonitorError> {
+    let current = get_control(display, control)?;
+
+    let value = match adjustment {
+        Adjustment::Absolute(value) => value.min(current.maximum),
+        Adjustment::Relative(delta) => {
+            (current.current as i64 + delta as i64).clamp(0, current.maximum as i64) as u16
+        }
+    };
+
+    display.set_vcp(control.code(), value, false)?;
+
+    Ok(value)
+}
+
+/// Whether the speakers are muted, from VCP feature 0x8D
+pub fn get_mute(display: &dyn ControlledDisplay) -> Result<bool, MonitorError> {
+    let value = display.get_vcp(0x8D, false)?;
+
+    match value.current & 0xff {
+        0x01 => Ok(true),
+        0x02 => Ok(false),
+        other => Err(MonitorError::Protocol {
+            message: format!("unknown mute state 0x{:02X}", other),
+        }),
+    }
+}
+
+pub fn set_mute(display: &dyn ControlledDisplay, muted: bool) -> Result<(), MonitorError> {
+    display.set_vcp(0x8D, if muted { 0x01 } else { 0x02 }, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::{backend::DisplayBackend, emulator::EmulatorBackend};
+
+    fn display() -> Box<dyn ControlledDisplay> {
+        EmulatorBackend::demo().enumerate().unwrap().remove(1)
+    }
+
+    #[test]
+    fn clamps_absolute_adjustments() {
+        let display = display();
+
+        assert_eq!(
+            adjust_control(&*display, Control::Volume, Adjustment::Absolute(40)),
+            Ok(40)
+        );
+        assert_eq!(
+            adjust_control(&*display, Control::Volume, Adjustment::Absolute(250)),
+            Ok(100)
+        );
+        assert_eq!(
+            get_control(&*display, Control::Volume).map(|v| v.current),
+            Ok(100)
+        );
+    }
+
+    #[test]
+    fn clamps_relative_adjustments() {
+        let display = display();
+
+        assert_eq!(
+            adjust_control(&*display, Control::Volume, Adjustment::Relative(-50)),
+            Ok(0)
+        );
+        assert_eq!(
+            adjust_control(&*display, Control::Volume, Adjustment::Relative(25)),
+            Ok(25)
+        );
+        assert_eq!(
+            adjust_control(&*display, Control::Volume, Adjustment::Relative(500)),
+            Ok(100)
+        );
+    }
+
+    #[test]
+    fn toggles_mute() {
+        let display = display();
+
+        assert_eq!(get_mute(&*display), Ok(false));
+        set_mute(&*display, true).unwrap();
+        assert_eq!(get_mute(&*display), Ok(true));
+    }
+}
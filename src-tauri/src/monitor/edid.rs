@@ -0,0 +1,435 @@
+use std::{error, fmt};
+
+const BLOCK_SIZE: usize = 128;
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+
+#[derive(Debug, Clone)]
+pub struct EdidError(&'static str);
+
+impl fmt::Display for EdidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EDID Error: {}", self.0)
+    }
+}
+
+impl error::Error for EdidError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PhysicalSize {
+    pub width_mm: u16,
+    pub height_mm: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DetailedTiming {
+    pub pixel_clock_khz: u32,
+    pub horizontal_active: u16,
+    pub horizontal_blanking: u16,
+    pub vertical_active: u16,
+    pub vertical_blanking: u16,
+    pub interlaced: bool,
+}
+
+impl DetailedTiming {
+    fn parse(descriptor: &[u8]) -> Option<DetailedTiming> {
+        let pixel_clock = u16::from_le_bytes([descriptor[0], descriptor[1]]) as u32;
+
+        // A zero pixel clock marks a display descriptor rather than a timing
+        if pixel_clock == 0 {
+            return None;
+        }
+
+        Some(DetailedTiming {
+            pixel_clock_khz: pixel_clock * 10,
+            horizontal_active: descriptor[2] as u16 | ((descriptor[4] as u16 & 0xF0) << 4),
+            horizontal_blanking: descriptor[3] as u16 | ((descriptor[4] as u16 & 0x0F) << 8),
+            vertical_active: descriptor[5] as u16 | ((descriptor[7] as u16 & 0xF0) << 4),
+            vertical_blanking: descriptor[6] as u16 | ((descriptor[7] as u16 & 0x0F) << 8),
+            interlaced: descriptor[17] & 0x80 != 0,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "type")]
+pub enum Extension {
+    /// CTA-861 (HDMI / consumer electronics) extension
+    Cta861 {
+        revision: u8,
+        underscan: bool,
+        basic_audio: bool,
+        hdmi: bool,
+        detailed_timings: Vec<DetailedTiming>,
+    },
+    /// DisplayID extension, only the version is decoded
+    DisplayId {
+        version: u8,
+    },
+    Unknown {
+        tag: u8,
+    },
+}
+
+impl Extension {
+    /// `None` for blocks that can't be decoded
+    fn parse(block: &[u8]) -> Option<Extension> {
+        match block[0] {
+            0x02 => {
+                // The DTDs start after the 4 byte header, or 0 when there are none
+                let dtd_offset = match block[2] as usize {
+                    1..=3 => return None,
+                    offset => offset.min(BLOCK_SIZE - 1),
+                };
+                let flags = block[3];
+
+                // Data block collection sits between the header and the DTDs
+                let mut hdmi = false;
+                let mut i = 4;
+                while dtd_offset > 4 && i < dtd_offset {
+                    let tag = block[i] >> 5;
+                    let len = (block[i] & 0x1F) as usize;
+
+                    // Vendor specific data block with the HDMI Licensing or HDMI Forum OUI
+                    if tag == 3 && len >= 3 && i + 3 < dtd_offset {
+                        let oui = [block[i + 3], block[i + 2], block[i + 1]];
+                        hdmi |= oui == [0x00, 0x0C, 0x03] || oui == [0xC4, 0x5D, 0xD8];
+                    }
+
+                    i += len + 1;
+                }
+
+                let detailed_timings = match dtd_offset {
+                    0 => vec![],
+                    offset => block[offset..BLOCK_SIZE - 1]
+                        .chunks_exact(18)
+                        .map_while(DetailedTiming::parse)
+                        .collect(),
+                };
+
+                Some(Extension::Cta861 {
+                    revision: block[1],
+                    underscan: flags & 0x80 != 0,
+                    basic_audio: flags & 0x40 != 0,
+                    hdmi,
+                    detailed_timings,
+                })
+            }
+            0x70 => Some(Extension::DisplayId { version: block[1] }),
+            tag => Some(Extension::Unknown { tag }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Edid {
+    pub manufacturer_id: String,
+    pub product_code: u16,
+    pub serial: Option<u32>,
+    pub manufacture_week: Option<u8>,
+    pub manufacture_year: Option<u16>,
+    pub model_year: Option<u16>,
+    pub version: (u8, u8),
+    pub monitor_name: Option<String>,
+    pub serial_number: Option<String>,
+    pub physical_size: Option<PhysicalSize>,
+    pub native_timing: Option<DetailedTiming>,
+    pub extensions: Vec<Extension>,
+}
+
+impl Edid {
+    pub fn parse(data: &[u8]) -> Result<Edid, EdidError> {
+        if data.len() < BLOCK_SIZE {
+            return Err(EdidError("base block is truncated"));
+        }
+
+        let base = &data[..BLOCK_SIZE];
+
+        if base[..8] != HEADER {
+            return Err(EdidError("invalid header"));
+        }
+
+        if !checksum_ok(base) {
+            return Err(EdidError("base block checksum mismatch"));
+        }
+
+        let manufacturer = u16::from_be_bytes([base[8], base[9]]);
+        let manufacturer_id = [10, 5, 0]
+            .iter()
+            .map(|shift| (((manufacturer >> shift) & 0x1F) as u8 + b'A' - 1) as char)
+            .collect();
+
+        let serial = u32::from_le_bytes([base[12], base[13], base[14], base[15]]);
+
+        // Week 0xFF means the year field is the model year instead of the manufacture year
+        let (manufacture_week, manufacture_year, model_year) = match base[16] {
+            0xFF => (None, None, Some(base[17] as u16 + 1990)),
+            0 => (None, Some(base[17] as u16 + 1990), None),
+            week => (Some(week), Some(base[17] as u16 + 1990), None),
+        };
+
+        // Zero in either dimension means the size is unknown or only an aspect ratio
+        let physical_size = match (base[21], base[22]) {
+            (0, _) | (_, 0) => None,
+            (width, height) => Some(PhysicalSize {
+                width_mm: width as u16 * 10,
+                height_mm: height as u16 * 10,
+            }),
+        };
+
+        let mut monitor_name = None;
+        let mut serial_number = None;
+        let mut native_timing = None;
+
+        for descriptor in base[54..126].chunks_exact(18) {
+            if let Some(timing) = DetailedTiming::parse(descriptor) {
+                // The first detailed timing is the preferred (native) one
+                native_timing.get_or_insert(timing);
+                continue;
+            }
+
+            match descriptor[3] {
+                0xFC => monitor_name = descriptor_text(descriptor),
+                0xFF => serial_number = descriptor_text(descriptor),
+                _ => {}
+            }
+        }
+
+        let extensions = data[BLOCK_SIZE..]
+            .chunks_exact(BLOCK_SIZE)
+            .take(base[126] as usize)
+            .filter(|block| checksum_ok(block))
+            .filter_map(Extension::parse)
+            .collect();
+
+        Ok(Edid {
+            manufacturer_id,
+            product_code: u16::from_le_bytes([base[10], base[11]]),
+            serial: if serial == 0 { None } else { Some(serial) },
+            manufacture_week,
+            manufacture_year,
+            model_year,
+            version: (base[18], base[19]),
+            monitor_name,
+            serial_number,
+            physical_size,
+            native_timing,
+            extensions,
+        })
+    }
+}
+
+fn checksum_ok(block: &[u8]) -> bool {
+    block.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) == 0
+}
+
+/// Display descriptor text is up to 13 bytes, terminated by a newline and space padded
+fn descriptor_text(descriptor: &[u8]) -> Option<String> {
+    let text: String = descriptor[5..18]
+        .iter()
+        .take_while(|&&c| c != 0x0A && c != 0x00)
+        .map(|&c| c as char)
+        .collect();
+
+    let text = text.trim();
+
+    match text.is_empty() {
+        true => None,
+        false => Some(text.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_checksum(mut block: Vec<u8>) -> Vec<u8> {
+        let sum = block[..127].iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        block[127] = 0u8.wrapping_sub(sum);
+
+        block
+    }
+
+    fn text_descriptor(tag: u8, text: &str) -> [u8; 18] {
+        let mut descriptor = [0u8; 18];
+        descriptor[3] = tag;
+        descriptor[5..18].fill(0x20);
+        descriptor[5..5 + text.len()].copy_from_slice(text.as_bytes());
+        descriptor[5 + text.len()] = 0x0A;
+
+        descriptor
+    }
+
+    /// Base block of a DELL U2720Q made in week 12 of 2020, announcing `extensions`
+    fn base_block(extensions: u8) -> Vec<u8> {
+        let mut block = vec![0u8; 128];
+        block[..8].copy_from_slice(&HEADER);
+        block[8..10].copy_from_slice(&[0x10, 0xAC]);
+        block[10..12].copy_from_slice(&0xA0F5u16.to_le_bytes());
+        block[12..16].copy_from_slice(&0x4C4D4E4Fu32.to_le_bytes());
+        block[16] = 12;
+        block[17] = 30;
+        block[18] = 1;
+        block[19] = 4;
+        block[21] = 60;
+        block[22] = 34;
+
+        // 3840x2160 at 533.25 MHz
+        block[54..72].copy_from_slice(&[
+            0x4D, 0xD0, 0x00, 0xA0, 0xF0, 0x70, 0x3E, 0x80, 0x30, 0x20, 0x35, 0x00, 0x54, 0x4F,
+            0x21, 0x00, 0x00, 0x1A,
+        ]);
+        block[72..90].copy_from_slice(&text_descriptor(0xFF, "F8KXK03"));
+        block[90..108].copy_from_slice(&text_descriptor(0xFC, "DELL U2720Q"));
+        block[111] = 0x10;
+        block[126] = extensions;
+
+        with_checksum(block)
+    }
+
+    /// CTA-861 block with an HDMI vendor data block and one DTD at `dtd_offset`
+    fn cta_block(dtd_offset: u8) -> Vec<u8> {
+        let mut block = vec![0u8; 128];
+        block[..4].copy_from_slice(&[0x02, 0x03, dtd_offset, 0xC0]);
+        block[4..9].copy_from_slice(&[0x64, 0x03, 0x0C, 0x00, 0x10]);
+
+        // 1920x1080 at 148.5 MHz
+        block[9..27].copy_from_slice(&[
+            0x02, 0x3A, 0x80, 0x18, 0x71, 0x38, 0x2D, 0x40, 0x58, 0x2C, 0x45, 0x00, 0x54, 0x4F,
+            0x21, 0x00, 0x00, 0x1E,
+        ]);
+
+        with_checksum(block)
+    }
+
+    #[test]
+    fn parses_base_block() {
+        let edid = Edid::parse(&base_block(0)).unwrap();
+
+        assert_eq!(edid.manufacturer_id, "DEL");
+        assert_eq!(edid.product_code, 0xA0F5);
+        assert_eq!(edid.serial, Some(0x4C4D4E4F));
+        assert_eq!(edid.version, (1, 4));
+        assert_eq!(
+            edid.physical_size,
+            Some(PhysicalSize {
+                width_mm: 600,
+                height_mm: 340
+            })
+        );
+        assert_eq!(
+            edid.native_timing,
+            Some(DetailedTiming {
+                pixel_clock_khz: 533_250,
+                horizontal_active: 3840,
+                horizontal_blanking: 160,
+                vertical_active: 2160,
+                vertical_blanking: 62,
+                interlaced: false,
+            })
+        );
+        assert!(edid.extensions.is_empty());
+    }
+
+    #[test]
+    fn parses_week_and_year() {
+        let edid = Edid::parse(&base_block(0)).unwrap();
+        assert_eq!(edid.manufacture_week, Some(12));
+        assert_eq!(edid.manufacture_year, Some(2020));
+        assert_eq!(edid.model_year, None);
+
+        let mut block = base_block(0);
+        block[16] = 0xFF;
+        let edid = Edid::parse(&with_checksum(block)).unwrap();
+        assert_eq!(edid.manufacture_week, None);
+        assert_eq!(edid.manufacture_year, None);
+        assert_eq!(edid.model_year, Some(2020));
+
+        let mut block = base_block(0);
+        block[16] = 0;
+        let edid = Edid::parse(&with_checksum(block)).unwrap();
+        assert_eq!(edid.manufacture_week, None);
+        assert_eq!(edid.manufacture_year, Some(2020));
+    }
+
+    #[test]
+    fn parses_name_and_serial_descriptors() {
+        let edid = Edid::parse(&base_block(0)).unwrap();
+
+        assert_eq!(edid.monitor_name.as_deref(), Some("DELL U2720Q"));
+        assert_eq!(edid.serial_number.as_deref(), Some("F8KXK03"));
+    }
+
+    #[test]
+    fn rejects_bad_header() {
+        let mut block = base_block(0);
+        block[0] = 0xFF;
+
+        let error = Edid::parse(&with_checksum(block)).unwrap_err();
+        assert_eq!(error.0, "invalid header");
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut block = base_block(0);
+        block[127] = block[127].wrapping_add(1);
+
+        let error = Edid::parse(&block).unwrap_err();
+        assert_eq!(error.0, "base block checksum mismatch");
+    }
+
+    #[test]
+    fn rejects_truncated_block() {
+        let error = Edid::parse(&base_block(0)[..100]).unwrap_err();
+        assert_eq!(error.0, "base block is truncated");
+    }
+
+    #[test]
+    fn parses_cta_extension() {
+        let edid = Edid::parse(&[base_block(1), cta_block(9)].concat()).unwrap();
+
+        assert_eq!(
+            edid.extensions,
+            [Extension::Cta861 {
+                revision: 3,
+                underscan: true,
+                basic_audio: true,
+                hdmi: true,
+                detailed_timings: vec![DetailedTiming {
+                    pixel_clock_khz: 148_500,
+                    horizontal_active: 1920,
+                    horizontal_blanking: 280,
+                    vertical_active: 1080,
+                    vertical_blanking: 45,
+                    interlaced: false,
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_extensions_with_bad_checksums() {
+        let mut cta = cta_block(9);
+        cta[127] = cta[127].wrapping_add(1);
+
+        let edid = Edid::parse(&[base_block(1), cta].concat()).unwrap();
+        assert!(edid.extensions.is_empty());
+    }
+
+    #[test]
+    fn skips_cta_extensions_with_invalid_dtd_offsets() {
+        for dtd_offset in 1..=3 {
+            let edid = Edid::parse(&[base_block(1), cta_block(dtd_offset)].concat()).unwrap();
+            assert!(edid.extensions.is_empty(), "offset {}", dtd_offset);
+        }
+
+        let edid = Edid::parse(&[base_block(1), cta_block(0)].concat()).unwrap();
+        assert!(matches!(
+            &edid.extensions[..],
+            [Extension::Cta861 { detailed_timings, .. }] if detailed_timings.is_empty()
+        ));
+    }
+}
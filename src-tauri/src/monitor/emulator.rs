@@ -0,0 +1,498 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    errors::MonitorError,
+    monitor::{
+        backend::{ControlledDisplay, DisplayBackend},
+        capabilities::MonitorCapabilities,
+        edid::Edid,
+        identity::MonitorId,
+        input::{inputs_from_capabilities, MonitorInput},
+    },
+};
+
+type Result<T> = std::result::Result<T, MonitorError>;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct EmulatedFeature {
+    pub code: u8,
+    pub value: u16,
+    pub maximum: u16,
+}
+
+/// A vendor feature that switches inputs as well as 0x60, e.g. 0xF4 on LG monitors
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct EmulatedInputFeature {
+    pub code: u8,
+    /// Pairs of a value of the feature and the 0x60 value it selects
+    pub inputs: Vec<(u16, u16)>,
+}
+
+/// Declarative description of an emulated monitor, everything the monitor reports
+/// over DDC/CI is derived from it.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct EmulatedMonitorDescription {
+    pub manufacturer_id: String,
+    pub product_code: u16,
+    pub serial: u32,
+    pub name: String,
+    pub resolution: (u16, u16),
+    pub capabilities: String,
+    pub features: Vec<EmulatedFeature>,
+    #[serde(default)]
+    pub input_feature: Option<EmulatedInputFeature>,
+}
+
+/// Builds single monitor descriptions for tests of the modules that sit on top of
+/// the backends.
+#[cfg(test)]
+impl EmulatedMonitorDescription {
+    /// A 1080p monitor without any features
+    pub fn test(name: &str, capabilities: &str) -> EmulatedMonitorDescription {
+        EmulatedMonitorDescription {
+            manufacturer_id: "ABC".to_string(),
+            product_code: 1,
+            serial: 1,
+            name: name.to_string(),
+            resolution: (1920, 1080),
+            capabilities: capabilities.to_string(),
+            features: vec![],
+            input_feature: None,
+        }
+    }
+
+    pub fn feature(mut self, code: u8, value: u16, maximum: u16) -> EmulatedMonitorDescription {
+        self.features.push(EmulatedFeature {
+            code,
+            value,
+            maximum,
+        });
+        self
+    }
+}
+
+/// The device side of an emulated monitor, answers requests the way a monitor
+/// would over DDC/CI.
+pub struct EmulatedDevice {
+    description: EmulatedMonitorDescription,
+    features: HashMap<u8, (u16, u16)>,
+}
+
+impl EmulatedDevice {
+    pub fn new(description: EmulatedMonitorDescription) -> EmulatedDevice {
+        let features = description
+            .features
+            .iter()
+            .map(|f| (f.code, (f.value, f.maximum)))
+            .collect();
+
+        EmulatedDevice {
+            description,
+            features,
+        }
+    }
+
+    pub fn capabilities_string(&self) -> Vec<u8> {
+        self.description.capabilities.as_bytes().to_vec()
+    }
+
+    /// Returns the current and maximum value of a feature
+    pub fn get_vcp_feature(&self, code: u8) -> Result<(u16, u16)> {
+        self.features
+            .get(&code)
+            .copied()
+            .ok_or(MonitorError::UnsupportedFeature { code })
+    }
+
+    pub fn set_vcp_feature(&mut self, code: u8, value: u16) -> Result<()> {
+        // A monitor in a power saving mode only reacts to being turned back on
+        if code != 0xD6 && !self.is_on() {
+            return Err(MonitorError::Timeout);
+        }
+
+        let feature = self
+            .features
+            .get_mut(&code)
+            .ok_or(MonitorError::UnsupportedFeature { code })?;
+
+        feature.0 = value;
+
+        self.sync_input_features(code, value);
+
+        Ok(())
+    }
+
+    fn is_on(&self) -> bool {
+        match self.features.get(&0xD6) {
+            Some((value, _)) => value & 0xff == 0x01,
+            None => true,
+        }
+    }
+
+    /// Keep 0x60 and the vendor input feature pointing at the same input
+    fn sync_input_features(&mut self, code: u8, value: u16) {
+        let input_feature = match &self.description.input_feature {
+            Some(input_feature) => input_feature,
+            None => return,
+        };
+
+        let linked = if code == input_feature.code {
+            input_feature
+                .inputs
+                .iter()
+                .find(|(vendor, _)| *vendor == value)
+                .map(|(_, standard)| (0x60, *standard))
+        } else if code == 0x60 {
+            input_feature
+                .inputs
+                .iter()
+                .find(|(_, standard)| *standard == value)
+                .map(|(vendor, _)| (input_feature.code, *vendor))
+        } else {
+            None
+        };
+
+        if let Some((code, value)) = linked {
+            if let Some(feature) = self.features.get_mut(&code) {
+                feature.0 = value;
+            }
+        }
+    }
+
+    pub fn read_edid(&self) -> Vec<u8> {
+        build_edid(&self.description)
+    }
+}
+
+/// Builds a 128 byte EDID 1.4 base block from the description.
+fn build_edid(description: &EmulatedMonitorDescription) -> Vec<u8> {
+    let mut edid = vec![0u8; 128];
+
+    edid[..8].copy_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+
+    let manufacturer = description
+        .manufacturer_id
+        .bytes()
+        .take(3)
+        .fold(0u16, |code, c| {
+            (code << 5) | (c.saturating_sub(b'@') & 0x1F) as u16
+        });
+    edid[8..10].copy_from_slice(&manufacturer.to_be_bytes());
+    edid[10..12].copy_from_slice(&description.product_code.to_le_bytes());
+    edid[12..16].copy_from_slice(&description.serial.to_le_bytes());
+
+    edid[16] = 1; // week
+    edid[17] = 30; // 2020
+    edid[18] = 1;
+    edid[19] = 4;
+    edid[20] = 0x80; // digital input
+    edid[21] = 60; // cm
+    edid[22] = 34;
+
+    // Native timing, reduced blanking at 60Hz
+    let (width, height) = description.resolution;
+    let (hblank, vblank) = (160u16, 41u16);
+    let pixel_clock = ((width + hblank) as u32 * (height + vblank) as u32 * 60 / 10_000)
+        .min(u16::MAX as u32) as u16;
+
+    let dtd = &mut edid[54..72];
+    dtd[0..2].copy_from_slice(&pixel_clock.to_le_bytes());
+    dtd[2] = width as u8;
+    dtd[3] = hblank as u8;
+    dtd[4] = (((width >> 8) as u8) << 4) | (hblank >> 8) as u8;
+    dtd[5] = height as u8;
+    dtd[6] = vblank as u8;
+    dtd[7] = (((height >> 8) as u8) << 4) | (vblank >> 8) as u8;
+
+    // Monitor name descriptor, newline terminated and space padded
+    let name = &mut edid[72..90];
+    name[3] = 0xFC;
+    let mut text = description.name.bytes().take(13).collect::<Vec<_>>();
+    if text.len() < 13 {
+        text.push(0x0A);
+    }
+    text.resize(13, 0x20);
+    name[5..18].copy_from_slice(&text);
+
+    // Remaining descriptors are dummies
+    edid[93] = 0x10;
+    edid[111] = 0x10;
+
+    let sum = edid[..127].iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    edid[127] = 0u8.wrapping_sub(sum);
+
+    edid
+}
+
+/// A monitor backed by an `EmulatedDevice`, built from what the device reports the
+/// same way a real monitor is.
+pub struct EmulatedMonitor {
+    pub id: MonitorId,
+    pub capabilities: Option<MonitorCapabilities>,
+    pub capabilities_error: Option<MonitorError>,
+    pub edid: Option<Edid>,
+    device: Arc<Mutex<EmulatedDevice>>,
+    inputs: Vec<MonitorInput>,
+}
+
+impl EmulatedMonitor {
+    pub fn new(device: Arc<Mutex<EmulatedDevice>>) -> Result<EmulatedMonitor> {
+        let (cap_string, edid_data) = {
+            let device = device.lock().map_err(|_| MonitorError::Busy)?;
+
+            (device.capabilities_string(), device.read_edid())
+        };
+
+        let edid = Edid::parse(&edid_data).ok();
+
+        let id = edid
+            .as_ref()
+            .and_then(MonitorId::from_edid)
+            .unwrap_or_else(|| MonitorId::from_bus_path("emulated"));
+
+        let (capabilities, capabilities_error, inputs) =
+            inputs_from_capabilities(MonitorCapabilities::from_reply(cap_string));
+
+        Ok(EmulatedMonitor {
+            id,
+            capabilities,
+            capabilities_error,
+            edid,
+            device,
+            inputs,
+        })
+    }
+}
+
+impl ControlledDisplay for EmulatedMonitor {
+    fn id(&self) -> &MonitorId {
+        &self.id
+    }
+
+    fn capabilities(&self) -> Option<&MonitorCapabilities> {
+        self.capabilities.as_ref()
+    }
+
+    fn capabilities_error(&self) -> Option<&MonitorError> {
+        self.capabilities_error.as_ref()
+    }
+
+    fn edid(&self) -> Option<&Edid> {
+        self.edid.as_ref()
+    }
+
+    fn get_inputs(&self) -> Result<Vec<MonitorInput>> {
+        Ok(self.inputs.clone())
+    }
+
+    fn get_vcp_feature(&self, code: u8) -> Result<(u16, u16)> {
+        self.device
+            .lock()
+            .map_err(|_| MonitorError::Busy)?
+            .get_vcp_feature(code)
+    }
+
+    fn set_vcp_feature(&self, code: u8, value: u16) -> Result<()> {
+        self.device
+            .lock()
+            .map_err(|_| MonitorError::Busy)?
+            .set_vcp_feature(code, value)
+    }
+
+    /// The emulated device listens to every source address
+    fn set_vcp_feature_from(&self, _source_address: u8, code: u8, value: u16) -> Result<()> {
+        self.set_vcp_feature(code, value)
+    }
+}
+
+/// Enumerates a fixed set of emulated monitors. Device state lives as long as the
+/// backend, so switches survive a refresh.
+pub struct EmulatorBackend {
+    devices: Vec<Arc<Mutex<EmulatedDevice>>>,
+}
+
+impl EmulatorBackend {
+    pub fn new(descriptions: Vec<EmulatedMonitorDescription>) -> EmulatorBackend {
+        EmulatorBackend {
+            devices: descriptions
+                .into_iter()
+                .map(|d| Arc::new(Mutex::new(EmulatedDevice::new(d))))
+                .collect(),
+        }
+    }
+
+    /// Load monitor descriptions from a JSON file
+    pub fn from_file(path: &Path) -> Result<EmulatorBackend> {
+        let json = fs::read_to_string(path).map_err(MonitorError::io)?;
+        let descriptions = serde_json::from_str(&json).map_err(MonitorError::io)?;
+
+        Ok(EmulatorBackend::new(descriptions))
+    }
+
+    /// A handful of monitors with typical capability strings, used by demo mode
+    pub fn demo() -> EmulatorBackend {
+        EmulatorBackend::new(vec![
+            EmulatedMonitorDescription {
+                manufacturer_id: "DEL".to_string(),
+                product_code: 0xA0F5,
+                serial: 0x4C4B3A31,
+                name: "DELL U2720Q".to_string(),
+                resolution: (3840, 2160),
+                capabilities: "(prot(monitor)type(LCD)model(U2720Q)cmds(01 02 03 07 0C E3 F3)vcp(02 04 05 08 10 12 14(01 04 05 06 08 09 0B 0C) 16 18 1A 52 60(0F 11 12 1B) AA(01 02 04) AC AE B2 B6 C6 C8 C9 D6(01 04 05) DC(00 03 05) DF E0 E1 E2(00 1D 02 04 0E 12 14 23 24 27) F0(0C) F1 F2 FD)mswhql(1)asset_eep(40)mccs_ver(2.1))".to_string(),
+                features: vec![
+                    EmulatedFeature { code: 0x10, value: 75, maximum: 100 },
+                    EmulatedFeature { code: 0x12, value: 75, maximum: 100 },
+                    EmulatedFeature { code: 0x60, value: 0x0F, maximum: 0x1B },
+                    EmulatedFeature { code: 0xD6, value: 0x01, maximum: 0x05 },
+                ],
+                input_feature: None,
+            },
+            EmulatedMonitorDescription {
+                manufacturer_id: "GSM".to_string(),
+                product_code: 0x5B7F,
+                serial: 0x0001E3C2,
+                name: "LG ULTRAGEAR".to_string(),
+                resolution: (2560, 1440),
+                capabilities: "(prot(monitor)type(LCD)model(27GL850)cmds(01 02 03 0C E3 F3)vcp(02 04 05 08 10 12 14(05 06 08 0B) 16 18 1A 52 60(11 12 0F 10) AC AE B2 B6 C0 C6 C8 C9 D6(01 04) DF 62 8D F4 F5(00 01 02) F6(00 01 02) 4D 4E 4F 15(01 06 09 10 11 13 14 28 29 32 44 48) F7(00 01 02 03) F8(00 01) F9 E4 E5 E6 E7 E8 E9 EA EB EF FD(00 01) FE(00 01 02) FF)mccs_ver(2.1)mswhql(1))".to_string(),
+                features: vec![
+                    EmulatedFeature { code: 0x10, value: 50, maximum: 100 },
+                    EmulatedFeature { code: 0x12, value: 70, maximum: 100 },
+                    EmulatedFeature { code: 0x60, value: 0x11, maximum: 0x12 },
+                    EmulatedFeature { code: 0x62, value: 30, maximum: 100 },
+                    EmulatedFeature { code: 0x8D, value: 0x02, maximum: 0x02 },
+                    EmulatedFeature { code: 0xD6, value: 0x01, maximum: 0x05 },
+                    EmulatedFeature { code: 0xF4, value: 0x90, maximum: 0xFF },
+                ],
+                input_feature: Some(EmulatedInputFeature {
+                    code: 0xF4,
+                    inputs: vec![(0x90, 0x11), (0x91, 0x12), (0xD0, 0x0F), (0xD1, 0x10)],
+                }),
+            },
+            EmulatedMonitorDescription {
+                manufacturer_id: "ACI".to_string(),
+                product_code: 0x24A2,
+                serial: 0x00004F1A,
+                name: "VG248".to_string(),
+                resolution: (1920, 1080),
+                capabilities: "(prot(monitor)type(lcd)model(VG248)cmds(01 02 03 07 0C F3)vcp(02 04 05 08 0B 0C 10 12 14(05 06 08 0B) 16 18 1A 60(01 03 11 0F) 62 6C 6E 70 8D(01 02) A8 AC AE B6 C6 C8 C9 CC(01 02 03 04 05 06 07 08 09 0A 0C 0D 11 12 14 1A 1E 1F 20 24 ) D6(01 04 05) DF)vcpname(60(Input Source(VGA DVI HDMI DisplayPort)))mccs_ver(2.1)asset_eep(32)mpu(01)mswhql(1))".to_string(),
+                features: vec![
+                    EmulatedFeature { code: 0x10, value: 90, maximum: 100 },
+                    EmulatedFeature { code: 0x12, value: 50, maximum: 100 },
+                    EmulatedFeature { code: 0x60, value: 0x03, maximum: 0x11 },
+                    EmulatedFeature { code: 0x62, value: 0, maximum: 100 },
+                    EmulatedFeature { code: 0x8D, value: 0x02, maximum: 0x02 },
+                    EmulatedFeature { code: 0xD6, value: 0x01, maximum: 0x05 },
+                ],
+                input_feature: None,
+            },
+        ])
+    }
+}
+
+impl DisplayBackend for EmulatorBackend {
+    fn name(&self) -> &'static str {
+        "emulator"
+    }
+
+    fn enumerate(&self) -> Result<Vec<Box<dyn ControlledDisplay>>> {
+        Ok(self
+            .devices
+            .iter()
+            .filter_map(|device| EmulatedMonitor::new(device.clone()).ok())
+            .map(|m| Box::new(m) as Box<dyn ControlledDisplay>)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::input::standard_inputs;
+
+    fn demo_displays() -> Vec<Box<dyn ControlledDisplay>> {
+        EmulatorBackend::demo().enumerate().unwrap()
+    }
+
+    #[test]
+    fn enumerates_demo_monitors() {
+        let displays = demo_displays();
+
+        let models: Vec<_> = displays
+            .iter()
+            .map(|d| d.capabilities().unwrap().display_model.as_str())
+            .collect();
+        assert_eq!(models, ["U2720Q", "27GL850", "VG248"]);
+
+        let edid = displays[0].edid().unwrap();
+        assert_eq!(edid.manufacturer_id, "DEL");
+        assert_eq!(edid.product_code, 0xA0F5);
+
+        assert_eq!(
+            displays[0].get_inputs().unwrap(),
+            [
+                MonitorInput::DisplayPort1,
+                MonitorInput::HDMI1,
+                MonitorInput::HDMI2,
+                MonitorInput::UsbC1
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_and_writes_vcp_features() {
+        let displays = demo_displays();
+        let display = &displays[0];
+
+        assert_eq!(display.get_vcp_feature(0x10), Ok((75, 100)));
+
+        display.set_vcp_feature(0x10, 20).unwrap();
+        assert_eq!(display.get_vcp_feature(0x10), Ok((20, 100)));
+
+        assert_eq!(
+            display.get_vcp_feature(0x62),
+            Err(MonitorError::UnsupportedFeature { code: 0x62 })
+        );
+        assert_eq!(
+            display.set_vcp_feature(0x62, 10),
+            Err(MonitorError::UnsupportedFeature { code: 0x62 })
+        );
+    }
+
+    #[test]
+    fn switches_input() {
+        let backend = EmulatorBackend::demo();
+        let displays = backend.enumerate().unwrap();
+
+        assert_eq!(
+            displays[0].get_current_input(),
+            Ok(MonitorInput::DisplayPort1)
+        );
+
+        displays[0].set_input(MonitorInput::UsbC1).unwrap();
+        assert_eq!(displays[0].get_current_input(), Ok(MonitorInput::UsbC1));
+
+        // Device state outlives the enumerated displays
+        let displays = backend.enumerate().unwrap();
+        assert_eq!(displays[0].get_current_input(), Ok(MonitorInput::UsbC1));
+    }
+
+    #[test]
+    fn missing_capabilities_fall_back_to_standard_inputs() {
+        let backend = EmulatorBackend::new(vec![EmulatedMonitorDescription::test("BROKEN", "")]);
+        let displays = backend.enumerate().unwrap();
+
+        assert!(displays[0].capabilities().is_none());
+        assert!(displays[0].capabilities_error().is_some());
+        assert_eq!(displays[0].get_inputs().unwrap(), standard_inputs());
+    }
+
+    #[test]
+    fn reports_unreadable_description_files() {
+        assert!(EmulatorBackend::from_file(Path::new("/nonexistent/monitors.json")).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+use self::Access::*;
+use self::FeatureKind::*;
+use self::MccsVersion::*;
+
+/// How the value of a VCP feature is interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureKind {
+    /// A value between 0 and the maximum the monitor reports, e.g. brightness
+    Continuous,
+    /// One of a fixed set of values, e.g. the input source
+    NonContinuous,
+    /// A block of bytes read and written with the table commands
+    Table,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+/// The MCCS version a feature was introduced in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+pub enum MccsVersion {
+    #[serde(rename = "2.0")]
+    V2_0,
+    #[serde(rename = "2.1")]
+    V2_1,
+    #[serde(rename = "2.2")]
+    V2_2,
+    #[serde(rename = "3.0")]
+    V3_0,
+}
+
+/// A VCP feature as MCCS defines it. Codes 0xE0 to 0xFF are left to
+/// manufacturers and aren't in the catalogue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct FeatureDefinition {
+    pub code: u8,
+    pub name: &'static str,
+    pub kind: FeatureKind,
+    pub access: Access,
+    /// Names of the values of a non-continuous feature, by their low byte
+    pub values: &'static [(u8, &'static str)],
+    pub since: MccsVersion,
+}
+
+impl FeatureDefinition {
+    pub fn value_name(&self, value: u8) -> Option<&'static str> {
+        self.values
+            .iter()
+            .find(|(v, _)| *v == value)
+            .map(|(_, name)| *name)
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.access != WriteOnly
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.access != ReadOnly
+    }
+}
+
+/// The definition of a feature, if MCCS defines it
+pub fn lookup(code: u8) -> Option<&'static FeatureDefinition> {
+    FEATURES
+        .binary_search_by_key(&code, |feature| feature.code)
+        .ok()
+        .map(|i| &FEATURES[i])
+}
+
+/// A read and write feature from MCCS 2.0, adjusted with the methods below
+const fn definition(code: u8, name: &'static str, kind: FeatureKind) -> FeatureDefinition {
+    FeatureDefinition {
+        code,
+        name,
+        kind,
+        access: ReadWrite,
+        values: &[],
+        since: V2_0,
+    }
+}
+
+const fn continuous(code: u8, name: &'static str) -> FeatureDefinition {
+    definition(code, name, Continuous)
+}
+
+const fn non_continuous(code: u8, name: &'static str) -> FeatureDefinition {
+    definition(code, name, NonContinuous)
+}
+
+const fn table(code: u8, name: &'static str) -> FeatureDefinition {
+    definition(code, name, Table)
+}
+
+impl FeatureDefinition {
+    const fn read_only(self) -> FeatureDefinition {
+        FeatureDefinition {
+            access: ReadOnly,
+            ..self
+        }
+    }
+
+    const fn write_only(self) -> FeatureDefinition {
+        FeatureDefinition {
+            access: WriteOnly,
+            ..self
+        }
+    }
+
+    const fn since(self, since: MccsVersion) -> FeatureDefinition {
+        FeatureDefinition { since, ..self }
+    }
+
+    const fn values(self, values: &'static [(u8, &'static str)]) -> FeatureDefinition {
+        FeatureDefinition { values, ..self }
+    }
+}
+
+const AUTO_SETUP_VALUES: &[(u8, &str)] = &[
+    (0x00, "Not active"),
+    (0x01, "Performing auto setup"),
+    (0x02, "Continuous auto setup"),
+];
+
+/// Every feature MCCS 2.0 to 3.0 defines, sorted by code
+pub const FEATURES: &[FeatureDefinition] = &[
+    non_continuous(0x01, "Degauss").write_only(),
+    non_continuous(0x02, "New Control Value").values(&[
+        (0x01, "No new control values"),
+        (0x02, "New control values saved"),
+        (0xFF, "No user controls present"),
+    ]),
+    non_continuous(0x03, "Soft Controls"),
+    non_continuous(0x04, "Restore Factory Defaults").write_only(),
+    non_continuous(0x05, "Restore Factory Brightness/Contrast Defaults").write_only(),
+    non_continuous(0x06, "Restore Factory Geometry Defaults").write_only(),
+    non_continuous(0x08, "Restore Factory Color Defaults").write_only(),
+    non_continuous(0x0A, "Restore Factory TV Defaults").write_only(),
+    non_continuous(0x0B, "Color Temperature Increment").read_only(),
+    continuous(0x0C, "Color Temperature Request"),
+    continuous(0x0E, "Clock"),
+    continuous(0x10, "Brightness"),
+    non_continuous(0x11, "Flesh Tone Enhancement").since(V2_2),
+    continuous(0x12, "Contrast"),
+    continuous(0x13, "Backlight Control"),
+    non_continuous(0x14, "Select Color Preset").values(&[
+        (0x01, "sRGB"),
+        (0x02, "Display Native"),
+        (0x03, "4000 K"),
+        (0x04, "5000 K"),
+        (0x05, "6500 K"),
+        (0x06, "7500 K"),
+        (0x07, "8200 K"),
+        (0x08, "9300 K"),
+        (0x09, "10000 K"),
+        (0x0A, "11500 K"),
+        (0x0B, "User 1"),
+        (0x0C, "User 2"),
+        (0x0D, "User 3"),
+    ]),
+    continuous(0x16, "Video Gain: Red"),
+    continuous(0x17, "User Color Vision Compensation").since(V2_2),
+    continuous(0x18, "Video Gain: Green"),
+    continuous(0x1A, "Video Gain: Blue"),
+    continuous(0x1C, "Focus"),
+    non_continuous(0x1E, "Auto Setup").values(AUTO_SETUP_VALUES),
+    non_continuous(0x1F, "Auto Color Setup").values(AUTO_SETUP_VALUES),
+    continuous(0x20, "Horizontal Position"),
+    continuous(0x22, "Horizontal Size"),
+    continuous(0x24, "Horizontal Pincushion"),
+    continuous(0x26, "Horizontal Pincushion Balance"),
+    continuous(0x28, "Horizontal Convergence R/B"),
+    continuous(0x29, "Horizontal Convergence M/G"),
+    continuous(0x2A, "Horizontal Linearity"),
+    continuous(0x2C, "Horizontal Linearity Balance"),
+    non_continuous(0x2E, "Gray Scale Expansion").since(V2_2),
+    continuous(0x30, "Vertical Position"),
+    continuous(0x32, "Vertical Size"),
+    continuous(0x34, "Vertical Pincushion"),
+    continuous(0x36, "Vertical Pincushion Balance"),
+    continuous(0x38, "Vertical Convergence R/B"),
+    continuous(0x39, "Vertical Convergence M/G"),
+    continuous(0x3A, "Vertical Linearity"),
+    continuous(0x3C, "Vertical Linearity Balance"),
+    continuous(0x3E, "Clock Phase"),
+    continuous(0x40, "Horizontal Parallelogram"),
+    continuous(0x41, "Vertical Parallelogram"),
+    continuous(0x42, "Horizontal Keystone"),
+    continuous(0x43, "Vertical Keystone"),
+    continuous(0x44, "Rotation"),
+    continuous(0x46, "Top Corner Flare"),
+    continuous(0x48, "Top Corner Hook"),
+    continuous(0x4A, "Bottom Corner Flare"),
+    continuous(0x4C, "Bottom Corner Hook"),
+    non_continuous(0x52, "Active Control").read_only(),
+    non_continuous(0x54, "Performance Preservation").since(V2_2),
+    continuous(0x56, "Horizontal Moire"),
+    continuous(0x58, "Vertical Moire"),
+    continuous(0x59, "6 Axis Saturation: Red").since(V2_2),
+    continuous(0x5A, "6 Axis Saturation: Yellow").since(V2_2),
+    continuous(0x5B, "6 Axis Saturation: Green").since(V2_2),
+    continuous(0x5C, "6 Axis Saturation: Cyan").since(V2_2),
+    continuous(0x5D, "6 Axis Saturation: Blue").since(V2_2),
+    continuous(0x5E, "6 Axis Saturation: Magenta").since(V2_2),
+    non_continuous(0x60, "Input Source").values(&[
+        (0x01, "Analog Video 1"),
+        (0x02, "Analog Video 2"),
+        (0x03, "DVI 1"),
+        (0x04, "DVI 2"),
+        (0x05, "Composite Video 1"),
+        (0x06, "Composite Video 2"),
+        (0x07, "S-Video 1"),
+        (0x08, "S-Video 2"),
+        (0x09, "Tuner 1"),
+        (0x0A, "Tuner 2"),
+        (0x0B, "Tuner 3"),
+        (0x0C, "Component Video 1"),
+        (0x0D, "Component Video 2"),
+        (0x0E, "Component Video 3"),
+        (0x0F, "DisplayPort 1"),
+        (0x10, "DisplayPort 2"),
+        (0x11, "HDMI 1"),
+        (0x12, "HDMI 2"),
+    ]),
+    continuous(0x62, "Audio: Speaker Volume"),
+    non_continuous(0x63, "Speaker Select").since(V2_2),
+    continuous(0x64, "Audio: Microphone Volume"),
+    non_continuous(0x66, "Ambient Light Sensor").since(V2_2),
+    continuous(0x6B, "Backlight Level: White").since(V2_2),
+    continuous(0x6C, "Video Black Level: Red"),
+    continuous(0x6D, "Backlight Level: Red").since(V2_2),
+    continuous(0x6E, "Video Black Level: Green"),
+    continuous(0x6F, "Backlight Level: Green").since(V2_2),
+    continuous(0x70, "Video Black Level: Blue"),
+    continuous(0x71, "Backlight Level: Blue").since(V2_2),
+    non_continuous(0x72, "Gamma").since(V2_2),
+    table(0x73, "LUT Size").read_only(),
+    table(0x74, "Single Point LUT Operation"),
+    table(0x75, "Block LUT Operation"),
+    table(0x76, "Remote Procedure Call").write_only(),
+    table(0x78, "Display Identification Data Operation")
+        .read_only()
+        .since(V2_1),
+    continuous(0x7A, "Adjust Focal Plane"),
+    continuous(0x7C, "Adjust Zoom"),
+    continuous(0x7E, "Trapezoid"),
+    continuous(0x80, "Keystone"),
+    non_continuous(0x82, "Horizontal Mirror").values(&[(0x00, "Normal"), (0x01, "Mirrored")]),
+    non_continuous(0x84, "Vertical Mirror").values(&[(0x00, "Normal"), (0x01, "Mirrored")]),
+    non_continuous(0x86, "Display Scaling").values(&[
+        (0x01, "No scaling"),
+        (0x02, "Full screen, keep aspect ratio"),
+        (0x03, "Full height"),
+        (0x04, "Full width"),
+        (0x05, "Full height, stretched"),
+        (0x06, "Full width, stretched"),
+        (0x07, "Linear expansion"),
+        (0x08, "Non-linear expansion"),
+    ]),
+    continuous(0x87, "Sharpness"),
+    continuous(0x88, "Velocity Scan Modulation"),
+    continuous(0x8A, "Color Saturation"),
+    non_continuous(0x8B, "TV Channel Up/Down").write_only(),
+    continuous(0x8C, "TV Sharpness"),
+    non_continuous(0x8D, "Audio Mute").values(&[(0x01, "Muted"), (0x02, "Unmuted")]),
+    continuous(0x8E, "TV Contrast"),
+    continuous(0x8F, "Audio: Treble"),
+    continuous(0x90, "Hue"),
+    continuous(0x91, "Audio: Bass"),
+    continuous(0x92, "TV Black Level"),
+    continuous(0x93, "Audio: Balance L/R"),
+    non_continuous(0x94, "Audio Processor Mode"),
+    continuous(0x95, "Window Position: Top Left X"),
+    continuous(0x96, "Window Position: Top Left Y"),
+    continuous(0x97, "Window Position: Bottom Right X"),
+    continuous(0x98, "Window Position: Bottom Right Y"),
+    non_continuous(0x99, "Window Control"),
+    continuous(0x9A, "Window Background"),
+    continuous(0x9B, "6 Axis Hue: Red").since(V2_2),
+    continuous(0x9C, "6 Axis Hue: Yellow").since(V2_2),
+    continuous(0x9D, "6 Axis Hue: Green").since(V2_2),
+    continuous(0x9E, "6 Axis Hue: Cyan").since(V2_2),
+    continuous(0x9F, "6 Axis Hue: Blue").since(V2_2),
+    continuous(0xA0, "6 Axis Hue: Magenta").since(V2_2),
+    non_continuous(0xA2, "Auto Setup On/Off").write_only(),
+    table(0xA4, "Window Mask Control").since(V2_2),
+    non_continuous(0xA5, "Window Select").since(V2_2),
+    non_continuous(0xAA, "Screen Orientation")
+        .read_only()
+        .values(&[
+            (0x01, "0 degrees"),
+            (0x02, "90 degrees"),
+            (0x03, "180 degrees"),
+            (0x04, "270 degrees"),
+            (0xFF, "Not applicable"),
+        ]),
+    continuous(0xAC, "Horizontal Frequency").read_only(),
+    continuous(0xAE, "Vertical Frequency").read_only(),
+    non_continuous(0xB0, "Settings").write_only().values(&[
+        (0x01, "Store current settings"),
+        (0x02, "Restore factory settings"),
+    ]),
+    non_continuous(0xB2, "Flat Panel Sub-Pixel Layout").read_only(),
+    table(0xB4, "Source Timing Mode").since(V2_2),
+    non_continuous(0xB6, "Display Technology Type").read_only(),
+    non_continuous(0xB7, "Monitor Status")
+        .read_only()
+        .since(V2_2),
+    continuous(0xB8, "Packet Count").since(V3_0),
+    continuous(0xB9, "Monitor X Origin").since(V3_0),
+    continuous(0xBA, "Monitor Y Origin").since(V3_0),
+    continuous(0xBB, "Header Error Count").since(V3_0),
+    continuous(0xBC, "Body CRC Error Count").since(V3_0),
+    continuous(0xBD, "Client ID").since(V3_0),
+    non_continuous(0xBE, "Link Control").since(V3_0),
+    continuous(0xC0, "Display Usage Time").read_only(),
+    continuous(0xC2, "Display Descriptor Length").read_only(),
+    table(0xC3, "Transmit Display Descriptor"),
+    non_continuous(0xC4, "Enable Display of Display Descriptor"),
+    non_continuous(0xC6, "Application Enable Key").read_only(),
+    non_continuous(0xC8, "Display Controller Type").read_only(),
+    continuous(0xC9, "Display Firmware Level").read_only(),
+    non_continuous(0xCA, "OSD").values(&[(0x01, "Disabled"), (0x02, "Enabled")]),
+    non_continuous(0xCC, "OSD Language").values(&[
+        (0x01, "Chinese (traditional)"),
+        (0x02, "English"),
+        (0x03, "French"),
+        (0x04, "German"),
+        (0x05, "Italian"),
+        (0x06, "Japanese"),
+        (0x07, "Korean"),
+        (0x08, "Portuguese (Portugal)"),
+        (0x09, "Russian"),
+        (0x0A, "Spanish"),
+        (0x0B, "Swedish"),
+        (0x0C, "Turkish"),
+        (0x0D, "Chinese (simplified)"),
+        (0x0E, "Portuguese (Brazil)"),
+        (0x0F, "Arabic"),
+        (0x10, "Bulgarian"),
+        (0x11, "Croatian"),
+        (0x12, "Czech"),
+        (0x13, "Danish"),
+        (0x14, "Dutch"),
+        (0x15, "Estonian"),
+        (0x16, "Finnish"),
+        (0x17, "Greek"),
+        (0x18, "Hebrew"),
+        (0x19, "Hindi"),
+        (0x1A, "Hungarian"),
+        (0x1B, "Latvian"),
+        (0x1C, "Lithuanian"),
+        (0x1D, "Norwegian"),
+        (0x1E, "Polish"),
+        (0x1F, "Romanian"),
+        (0x20, "Serbian"),
+        (0x21, "Slovak"),
+        (0x22, "Slovenian"),
+        (0x23, "Thai"),
+        (0x24, "Ukrainian"),
+        (0x25, "Vietnamese"),
+    ]),
+    non_continuous(0xCD, "Status Indicators").since(V2_2),
+    non_continuous(0xCE, "Auxiliary Display Size").read_only(),
+    table(0xCF, "Auxiliary Display Data").write_only(),
+    non_continuous(0xD0, "Output Select"),
+    table(0xD2, "Asset Tag"),
+    non_continuous(0xD4, "Stereo Video Mode"),
+    non_continuous(0xD6, "Power Mode").values(&[
+        (0x01, "On"),
+        (0x02, "Standby"),
+        (0x03, "Suspend"),
+        (0x04, "Off"),
+        (0x05, "Off (power switch)"),
+    ]),
+    non_continuous(0xD7, "Auxiliary Power Output").since(V2_2),
+    non_continuous(0xDA, "Scan Mode"),
+    non_continuous(0xDB, "Image Mode").since(V2_2),
+    non_continuous(0xDC, "Display Mode").values(&[
+        (0x00, "Standard"),
+        (0x01, "Productivity"),
+        (0x02, "Mixed"),
+        (0x03, "Movie"),
+        (0x04, "User defined"),
+        (0x05, "Games"),
+        (0x06, "Sports"),
+        (0x07, "Professional"),
+        (0x08, "Standard, intermediate power"),
+        (0x09, "Standard, low power"),
+        (0x0A, "Demonstration"),
+        (0xF0, "Dynamic contrast"),
+    ]),
+    non_continuous(0xDE, "Scratch Pad"),
+    non_continuous(0xDF, "VCP Version").read_only(),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn features_are_sorted_without_duplicates() {
+        for pair in FEATURES.windows(2) {
+            assert!(
+                pair[0].code < pair[1].code,
+                "0x{:02X} has to come before 0x{:02X}",
+                pair[0].code,
+                pair[1].code
+            );
+        }
+    }
+
+    #[test]
+    fn looks_up_known_codes() {
+        let brightness = lookup(0x10).unwrap();
+        assert_eq!(brightness.name, "Brightness");
+        assert_eq!(brightness.kind, Continuous);
+        assert!(brightness.is_readable() && brightness.is_writable());
+
+        let input = lookup(0x60).unwrap();
+        assert_eq!(input.value_name(0x0F), Some("DisplayPort 1"));
+        assert_eq!(input.value_name(0x1B), None);
+
+        assert!(!lookup(0x01).unwrap().is_readable());
+        assert!(!lookup(0xDF).unwrap().is_writable());
+    }
+
+    #[test]
+    fn unknown_and_manufacturer_codes_are_missing() {
+        assert!(lookup(0x00).is_none());
+        assert!(lookup(0x07).is_none());
+        assert!(lookup(0x7F).is_none());
+
+        for code in 0xE0..=0xFF {
+            assert!(lookup(code).is_none(), "0x{:02X}", code);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+
+use crate::monitor::edid::Edid;
+
+/// Identifies a monitor across enumerations, independent of the order in which
+/// displays are probed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct MonitorId(String);
+
+impl MonitorId {
+    /// Build an id from the EDID vendor block, e.g. `DEL-A0B1-12345678`.
+    ///
+    /// Many monitors leave the numeric serial as zero, in which case the serial
+    /// number descriptor is used instead.
+    pub fn from_edid(edid: &Edid) -> Option<MonitorId> {
+        let serial = match (edid.serial, &edid.serial_number) {
+            (Some(serial), _) => format!("{:08X}", serial),
+            (None, Some(serial_number)) => serial_number.clone(),
+            _ => return None,
+        };
+
+        Some(MonitorId(format!(
+            "{}-{:04X}-{}",
+            edid.manufacturer_id, edid.product_code, serial
+        )))
+    }
+
+    /// Build an id from the bus the monitor is connected to. Stable as long as
+    /// the monitor stays plugged into the same port.
+    pub fn from_bus_path(path: &str) -> MonitorId {
+        MonitorId(format!("bus:{}", path))
+    }
+
+    /// Disambiguate ids that collide, e.g. two identical monitors without serials.
+    pub fn with_suffix(&self, n: usize) -> MonitorId {
+        MonitorId(format!("{}#{}", self.0, n))
+    }
+}
+
+impl fmt::Display for MonitorId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Append a suffix to every id after the first one that collides.
+pub fn dedup_ids<'a>(ids: impl Iterator<Item = &'a mut MonitorId>) {
+    let mut seen: Vec<MonitorId> = vec![];
+
+    for id in ids {
+        let original = id.clone();
+        let count = seen.iter().filter(|s| **s == original).count();
+
+        if count > 0 {
+            *id = original.with_suffix(count + 1);
+        }
+
+        seen.push(original);
+    }
+}
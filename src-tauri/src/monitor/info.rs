@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use crate::monitor::{
+    backend::{EnumeratedDisplay, VcpValue},
+    capabilities::AudioFeatures,
+    controls::{get_control, get_mute, Control},
+    edid::Edid,
+    identity::MonitorId,
+    input::{input_name, MonitorInput},
+};
+
+#[derive(serde::Serialize, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CapabilitiesStatus {
+    Available,
+    /// The monitor is still listed, with a fallback input list
+    Unavailable {
+        reason: String,
+    },
+}
+
+/// Snapshot of a monitor's state, sent to the frontend and printed by the CLI.
+#[derive(serde::Serialize, Clone)]
+pub struct MonitorInfo {
+    pub id: MonitorId,
+    pub backend: &'static str,
+    pub model: String,
+    pub inputs: Vec<MonitorInput>,
+    /// Label of every input, from the monitor's `vcpname` section when it has one
+    pub input_names: HashMap<MonitorInput, String>,
+    pub current_input: Option<MonitorInput>,
+    /// `None` when the monitor doesn't support or didn't answer the read
+    pub brightness: Option<VcpValue>,
+    pub contrast: Option<VcpValue>,
+    /// Audio features are only read from monitors that list them, or whose
+    /// capabilities couldn't be read
+    pub volume: Option<VcpValue>,
+    pub muted: Option<bool>,
+    pub treble: Option<VcpValue>,
+    pub bass: Option<VcpValue>,
+    pub capabilities: CapabilitiesStatus,
+    pub edid: Option<Edid>,
+}
+
+impl From<&EnumeratedDisplay> for MonitorInfo {
+    fn from(enumerated: &EnumeratedDisplay) -> Self {
+        let m = &enumerated.display;
+
+        let mut display_model = m
+            .capabilities()
+            .map(|c| c.clone().display_model)
+            .unwrap_or("".to_string());
+
+        if display_model.is_empty() {
+            display_model = m
+                .edid()
+                .and_then(|e| e.monitor_name.clone())
+                .unwrap_or("Generic Display".to_string());
+        }
+
+        let inputs = m.get_inputs().unwrap_or(vec![]);
+        let input_names = inputs
+            .iter()
+            .map(|input| (*input, input_name(m.capabilities(), *input)))
+            .collect();
+
+        // Without a capability string try every audio feature, reads of the ones
+        // the monitor lacks fail and leave them out
+        let audio = m.capabilities().map_or(
+            AudioFeatures {
+                volume: true,
+                mute: true,
+                treble: true,
+                bass: true,
+            },
+            |c| c.audio_features(),
+        );
+        let read = |supported: bool, control: Control| {
+            supported
+                .then(|| get_control(m.as_ref(), control).ok())
+                .flatten()
+        };
+
+        MonitorInfo {
+            id: m.id().clone(),
+            backend: enumerated.backend,
+            model: display_model,
+            inputs,
+            input_names,
+            current_input: m.get_current_input().ok(),
+            brightness: get_control(m.as_ref(), Control::Brightness).ok(),
+            contrast: get_control(m.as_ref(), Control::Contrast).ok(),
+            volume: read(audio.volume, Control::Volume),
+            muted: audio.mute.then(|| get_mute(m.as_ref()).ok()).flatten(),
+            treble: read(audio.treble, Control::Treble),
+            bass: read(audio.bass, Control::Bass),
+            capabilities: match m.capabilities_error() {
+                Some(e) => CapabilitiesStatus::Unavailable {
+                    reason: e.to_string(),
+                },
+                None => CapabilitiesStatus::Available,
+            },
+            edid: m.edid().cloned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::{
+        backend::BackendRegistry,
+        emulator::{EmulatedMonitorDescription, EmulatorBackend},
+    };
+
+    fn info(capabilities: &str) -> MonitorInfo {
+        let mut registry = BackendRegistry::default();
+        registry.register(EmulatorBackend::new(vec![
+            EmulatedMonitorDescription::test("SPEAKERS", capabilities)
+                .feature(0x62, 30, 100)
+                .feature(0x8D, 0x01, 0x02),
+        ]));
+
+        MonitorInfo::from(&registry.enumerate().displays[0])
+    }
+
+    #[test]
+    fn reads_listed_audio_features() {
+        let info = info("(prot(monitor)model(SPEAKERS)vcp(10 62)mccs_ver(2.1))");
+
+        assert_eq!(info.volume.map(|v| v.current), Some(30));
+        assert_eq!(info.muted, None);
+        assert_eq!(info.treble, None);
+    }
+
+    #[test]
+    fn reads_audio_features_without_capabilities() {
+        let info = info("");
+
+        assert_eq!(info.volume.map(|v| v.current), Some(30));
+        assert_eq!(info.muted, Some(true));
+        assert_eq!(info.treble, None);
+        assert_eq!(info.bass, None);
+    }
+}
@@ -1,10 +1,16 @@
 use crate::errors::MonitorError;
-use crate::monitor::capabilities::MonitorCapabilities;
-use std::fmt;
+use crate::monitor::{capabilities::MonitorCapabilities, mccs};
+use std::{fmt, str::FromStr};
 
-#[derive(Debug, FromPrimitive, Copy, Clone, serde::Serialize, serde::Deserialize)]
+/// An input source, as read from and written to VCP feature 0x60.
+///
+/// MCCS only names the codes up to 0x12, everything above is reserved by the
+/// standard but used by vendors for USB-C, extra HDMI/DP ports and so on. The
+/// common ones are named, the rest keep their raw value in `Other` so they can
+/// still be switched to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MonitorInput {
-    AnalogVideo1 = 1,
+    AnalogVideo1,
     AnalogVideo2,
     DVI1,
     DVI2,
@@ -22,8 +28,90 @@ pub enum MonitorInput {
     DisplayPort2,
     HDMI1,
     HDMI2,
-    Unknown,
-    Reserved,
+    HDMI3,
+    HDMI4,
+    /// USB-C (DisplayPort alt mode), 0x1B on Dell and most monitors following it
+    UsbC1,
+    UsbC2,
+    Other(u8),
+}
+
+/// Every input with a known name, in VCP code order
+const NAMED_INPUTS: [MonitorInput; 22] = [
+    MonitorInput::AnalogVideo1,
+    MonitorInput::AnalogVideo2,
+    MonitorInput::DVI1,
+    MonitorInput::DVI2,
+    MonitorInput::CompositeVideo1,
+    MonitorInput::CompositeVideo2,
+    MonitorInput::SVideo1,
+    MonitorInput::SVideo2,
+    MonitorInput::Tuner1,
+    MonitorInput::Tuner2,
+    MonitorInput::Tuner3,
+    MonitorInput::ComponentVideo1,
+    MonitorInput::ComponentVideo2,
+    MonitorInput::ComponentVideo3,
+    MonitorInput::DisplayPort1,
+    MonitorInput::DisplayPort2,
+    MonitorInput::HDMI1,
+    MonitorInput::HDMI2,
+    MonitorInput::HDMI3,
+    MonitorInput::HDMI4,
+    MonitorInput::UsbC1,
+    MonitorInput::UsbC2,
+];
+
+impl MonitorInput {
+    /// The raw VCP 0x60 value
+    pub fn code(&self) -> u8 {
+        use MonitorInput::*;
+
+        match self {
+            AnalogVideo1 => 0x01,
+            AnalogVideo2 => 0x02,
+            DVI1 => 0x03,
+            DVI2 => 0x04,
+            CompositeVideo1 => 0x05,
+            CompositeVideo2 => 0x06,
+            SVideo1 => 0x07,
+            SVideo2 => 0x08,
+            Tuner1 => 0x09,
+            Tuner2 => 0x0A,
+            Tuner3 => 0x0B,
+            ComponentVideo1 => 0x0C,
+            ComponentVideo2 => 0x0D,
+            ComponentVideo3 => 0x0E,
+            DisplayPort1 => 0x0F,
+            DisplayPort2 => 0x10,
+            HDMI1 => 0x11,
+            HDMI2 => 0x12,
+            HDMI3 => 0x13,
+            HDMI4 => 0x14,
+            UsbC1 => 0x1B,
+            UsbC2 => 0x1C,
+            Other(code) => *code,
+        }
+    }
+
+    /// Stable identifier used in settings and sent to the frontend, the variant
+    /// name for known inputs and the hex code otherwise.
+    pub fn key(&self) -> String {
+        match self {
+            MonitorInput::Other(code) => format!("0x{:02X}", code),
+            input => format!("{:?}", input),
+        }
+    }
+}
+
+impl From<u8> for MonitorInput {
+    fn from(code: u8) -> Self {
+        NAMED_INPUTS
+            .iter()
+            .copied()
+            .find(|input| input.code() == code)
+            .unwrap_or(MonitorInput::Other(code))
+    }
 }
 
 impl fmt::Display for MonitorInput {
@@ -49,53 +137,150 @@ impl fmt::Display for MonitorInput {
             DisplayPort2 => "DP 2",
             HDMI1 => "HDMI 1",
             HDMI2 => "HDMI 2",
-            _ => "Unknown",
+            HDMI3 => "HDMI 3",
+            HDMI4 => "HDMI 4",
+            UsbC1 => "USB-C",
+            UsbC2 => "USB-C 2",
+            Other(code) => return write!(f, "Input 0x{:02X}", code),
         };
 
         write!(f, "{}", s)
     }
 }
 
+impl FromStr for MonitorInput {
+    type Err = MonitorError;
+
+    /// Accepts the variant name (`HDMI1`), the display name (`HDMI 1`) or the raw
+    /// VCP 0x60 value in hex, with or without a `0x` prefix, the way capability
+    /// strings list it.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(code) = mccs::parse_code(s) {
+            return Ok(MonitorInput::from(code));
+        }
+
+        let normalize = |s: &str| s.replace(' ', "").to_lowercase();
+        let wanted = normalize(s);
+
+        NAMED_INPUTS
+            .iter()
+            .copied()
+            .find(|input| {
+                normalize(&input.key()) == wanted || normalize(&input.to_string()) == wanted
+            })
+            .ok_or(MonitorError::InvalidInput {
+                input: s.to_string(),
+            })
+    }
+}
+
+impl serde::Serialize for MonitorInput {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.key())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MonitorInput {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let key = String::deserialize(deserializer)?;
+
+        key.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The manufacturer's name for an input from the `vcpname` section, or its
+/// standard name
+pub fn input_name(capabilities: Option<&MonitorCapabilities>, input: MonitorInput) -> String {
+    capabilities
+        .and_then(|c| c.value_name(0x60, input.code() as u16))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| input.to_string())
+}
+
+/// Every input MCCS defines, for monitors that don't tell which ones they have
+pub fn standard_inputs() -> Vec<MonitorInput> {
+    (0x01..=0x12).map(MonitorInput::from).collect()
+}
+
+/// Split the outcome of reading a monitor's capabilities into the capabilities,
+/// why they couldn't be read, and the inputs to list. Monitors that can't describe
+/// themselves often still switch inputs fine, so they get every standard input.
+pub fn inputs_from_capabilities(
+    capabilities: Result<MonitorCapabilities, MonitorError>,
+) -> (
+    Option<MonitorCapabilities>,
+    Option<MonitorError>,
+    Vec<MonitorInput>,
+) {
+    match capabilities {
+        Ok(capabilities) => {
+            let inputs = get_all_inputs_from_capabilities_string(&capabilities).unwrap_or_default();
+            (Some(capabilities), None, inputs)
+        }
+        Err(e) => (None, Some(e), standard_inputs()),
+    }
+}
+
 pub fn get_all_inputs_from_capabilities_string(
     capabilities: &MonitorCapabilities,
 ) -> Result<Vec<MonitorInput>, MonitorError> {
-    let input_values = capabilities.vcp_codes.iter().find_map(|cmd| {
-        let has_command = &cmd.command == "60";
+    let inputs = match capabilities.vcp_feature(0x60) {
+        Some(cmd) => cmd.byte_values().map(MonitorInput::from).collect(),
+        None => vec![],
+    };
+
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_code_round_trips_through_serde() {
+        for code in 0..=255u8 {
+            let input = MonitorInput::from(code);
+            let json = serde_json::to_string(&input).unwrap();
+            let back: MonitorInput = serde_json::from_str(&json).unwrap();
 
-        match has_command {
-            true => Some(&cmd.values),
-            false => None,
+            assert_eq!(back.code(), code);
         }
-    });
+    }
 
-    if let Some(values) = input_values {
-        let inputs: Vec<_> = values
-            .iter()
-            .map(|value| match &value.command[..] {
-                "01" => MonitorInput::AnalogVideo1,
-                "02" => MonitorInput::AnalogVideo2,
-                "03" => MonitorInput::DVI1,
-                "04" => MonitorInput::DVI2,
-                "05" => MonitorInput::CompositeVideo1,
-                "06" => MonitorInput::CompositeVideo2,
-                "07" => MonitorInput::SVideo1,
-                "08" => MonitorInput::SVideo2,
-                "09" => MonitorInput::Tuner1,
-                "0A" => MonitorInput::Tuner2,
-                "0B" => MonitorInput::Tuner3,
-                "0C" => MonitorInput::ComponentVideo1,
-                "0D" => MonitorInput::ComponentVideo2,
-                "0E" => MonitorInput::ComponentVideo3,
-                "0F" => MonitorInput::DisplayPort1,
-                "10" => MonitorInput::DisplayPort2,
-                "11" => MonitorInput::HDMI1,
-                "12" => MonitorInput::HDMI2,
-                _ => MonitorInput::Unknown,
-            })
-            .collect();
+    #[test]
+    fn names_vendor_codes() {
+        assert_eq!(MonitorInput::from(0x13), MonitorInput::HDMI3);
+        assert_eq!(MonitorInput::from(0x14), MonitorInput::HDMI4);
+        assert_eq!(MonitorInput::from(0x1B), MonitorInput::UsbC1);
+        assert_eq!(MonitorInput::from(0x1C), MonitorInput::UsbC2);
+        assert_eq!(MonitorInput::from(0x90), MonitorInput::Other(0x90));
 
-        return Ok(inputs);
+        assert_eq!(MonitorInput::HDMI3.to_string(), "HDMI 3");
+        assert_eq!(
+            serde_json::to_string(&MonitorInput::UsbC2).unwrap(),
+            "\"UsbC2\""
+        );
+        assert_eq!(
+            serde_json::to_string(&MonitorInput::Other(0x90)).unwrap(),
+            "\"0x90\""
+        );
     }
 
-    Ok(vec![])
+    #[test]
+    fn parses_names_and_codes() {
+        assert_eq!("HDMI4".parse(), Ok(MonitorInput::HDMI4));
+        assert_eq!("usb-c 2".parse(), Ok(MonitorInput::UsbC2));
+        assert_eq!("0x1b".parse(), Ok(MonitorInput::UsbC1));
+        assert!("HDMI 9".parse::<MonitorInput>().is_err());
+    }
+
+    #[test]
+    fn parses_bare_codes_as_hex() {
+        assert_eq!("11".parse(), Ok(MonitorInput::HDMI1));
+        assert_eq!("0F".parse(), Ok(MonitorInput::DisplayPort1));
+        assert_eq!("1b".parse(), Ok(MonitorInput::UsbC1));
+        assert_eq!("90".parse(), Ok(MonitorInput::Other(0x90)));
+        assert!("100".parse::<MonitorInput>().is_err());
+        assert!("HDMI 9".parse::<MonitorInput>().is_err());
+    }
 }
@@ -1,107 +1,217 @@
 use std::{
-    cell::{BorrowMutError, RefCell},
+    cell::RefCell,
     convert::TryFrom,
-    string::FromUtf8Error,
+    io::{self, Write},
 };
 
 use ddc_hi::{Ddc, Display, Handle};
 
 use crate::{
     errors::MonitorError,
-    mccs::ParserError,
     monitor::{
+        backend::{ControlledDisplay, HOST_SOURCE_ADDRESS},
         capabilities::MonitorCapabilities,
-        input::{get_all_inputs_from_capabilities_string, MonitorInput},
+        edid::Edid,
+        identity::{dedup_ids, MonitorId},
+        input::{inputs_from_capabilities, MonitorInput},
     },
 };
 
 type Result<T> = std::result::Result<T, MonitorError>;
 
+/// I2C address monitors answer DDC/CI on
+const DDC_CI_ADDRESS: u8 = 0x37;
+
 pub struct Monitor {
-    pub id: u8,
+    pub id: MonitorId,
     pub capabilities: Option<MonitorCapabilities>,
+    pub capabilities_error: Option<MonitorError>,
+    pub edid: Option<Edid>,
     handle: RefCell<Handle>,
     inputs: Vec<MonitorInput>,
 }
 
 impl Monitor {
     pub fn get_all_monitors() -> Result<Vec<Monitor>> {
-        let monitors = Display::enumerate()
+        let mut monitors: Vec<Monitor> = Display::enumerate()
             .into_iter()
-            .enumerate()
-            .filter_map(|(i, display)| {
-                Some(Monitor {
-                    id: i as u8,
-                    ..display.try_into().ok()?
-                })
-            });
-
-        Ok(monitors.collect())
+            .filter_map(|display| display.try_into().ok())
+            .collect();
+
+        dedup_ids(monitors.iter_mut().map(|m| &mut m.id));
+
+        Ok(monitors)
     }
+}
 
-    pub fn get_inputs(&self) -> Result<Vec<MonitorInput>> {
+impl ControlledDisplay for Monitor {
+    fn id(&self) -> &MonitorId {
+        &self.id
+    }
+
+    fn capabilities(&self) -> Option<&MonitorCapabilities> {
+        self.capabilities.as_ref()
+    }
+
+    fn capabilities_error(&self) -> Option<&MonitorError> {
+        self.capabilities_error.as_ref()
+    }
+
+    fn edid(&self) -> Option<&Edid> {
+        self.edid.as_ref()
+    }
+
+    fn get_inputs(&self) -> Result<Vec<MonitorInput>> {
         Ok(self.inputs.clone())
     }
 
-    pub fn set_input(&self, input: MonitorInput) -> Result<()> {
-        Ok(self
+    fn get_vcp_feature(&self, code: u8) -> Result<(u16, u16)> {
+        let value = self
             .handle
-            .try_borrow_mut()?
-            .set_vcp_feature(0x60, input as u16)?)
+            .try_borrow_mut()
+            .map_err(|_| MonitorError::Busy)?
+            .get_vcp_feature(code)
+            .map_err(|e| ddc_error(e, Some(code)))?;
+
+        Ok((value.value(), value.maximum()))
     }
+
+    fn set_vcp_feature(&self, code: u8, value: u16) -> Result<()> {
+        self.handle
+            .try_borrow_mut()
+            .map_err(|_| MonitorError::Busy)?
+            .set_vcp_feature(code, value)
+            .map_err(|e| ddc_error(e, Some(code)))
+    }
+
+    /// ddc-hi always writes from the host address, so the packet is framed here
+    fn set_vcp_feature_from(&self, source_address: u8, code: u8, value: u16) -> Result<()> {
+        if source_address == HOST_SOURCE_ADDRESS {
+            return self.set_vcp_feature(code, value);
+        }
+
+        let mut handle = self
+            .handle
+            .try_borrow_mut()
+            .map_err(|_| MonitorError::Busy)?;
+
+        let i2c = match &mut *handle {
+            Handle::I2cDevice(ddc) => ddc.inner_mut(),
+        };
+
+        i2c.smbus_set_slave_address(DDC_CI_ADDRESS as u16, false)?;
+        i2c.write_all(&set_vcp_packet(source_address, code, value))?;
+
+        Ok(())
+    }
+}
+
+/// A DDC/CI Set VCP Feature request: the source address, the length of the data
+/// with the high bit set, the data, and a checksum that XORs every byte with the
+/// monitor's write address.
+fn set_vcp_packet(source_address: u8, code: u8, value: u16) -> Vec<u8> {
+    let [high, low] = value.to_be_bytes();
+    let data = [0x03, code, high, low];
+
+    let mut packet = vec![source_address, 0x80 | data.len() as u8];
+    packet.extend_from_slice(&data);
+
+    let checksum = packet.iter().fold(DDC_CI_ADDRESS << 1, |sum, b| sum ^ b);
+    packet.push(checksum);
+
+    packet
 }
 
 impl TryFrom<Display> for Monitor {
     type Error = MonitorError;
 
     fn try_from(mut val: Display) -> std::result::Result<Self, Self::Error> {
-        let capabilities = Some(MonitorCapabilities::from_cap_string(String::from_utf8(
-            val.handle.capabilities_string()?,
-        )?)?);
-
-        let inputs = get_all_inputs_from_capabilities_string(capabilities.as_ref().unwrap())?;
+        let edid = val
+            .info
+            .edid_data
+            .as_ref()
+            .and_then(|data| Edid::parse(data).ok());
+
+        let id = edid
+            .as_ref()
+            .and_then(MonitorId::from_edid)
+            .unwrap_or_else(|| MonitorId::from_bus_path(&i2c_bus_path(&val.info.id)));
+
+        let (capabilities, capabilities_error, inputs) = inputs_from_capabilities(
+            val.handle
+                .capabilities_string()
+                .map_err(|e| ddc_error(e, None))
+                .and_then(MonitorCapabilities::from_reply),
+        );
 
         Ok(Monitor {
-            id: 0,
+            id,
             capabilities,
+            capabilities_error,
+            edid,
             handle: RefCell::new(val.handle),
             inputs,
         })
     }
 }
 
-impl From<anyhow::Error> for MonitorError {
-    fn from(val: anyhow::Error) -> Self {
-        // Leak the error description
-        let error: &'static mut String = Box::leak(Box::new(val.to_string()));
-
-        Self(error.as_str())
+/// ddc-hi identifies i2c displays by the device number of their `/dev/i2c-*`
+/// node, the bus number is the device minor.
+fn i2c_bus_path(rdev: &str) -> String {
+    match rdev.parse::<u64>() {
+        Ok(rdev) => {
+            let minor = ((rdev >> 12) & 0xffff_ff00) | (rdev & 0xff);
+            format!("/dev/i2c-{}", minor)
+        }
+        Err(_) => rdev.to_string(),
     }
 }
 
-impl From<FromUtf8Error> for MonitorError {
-    fn from(val: FromUtf8Error) -> Self {
-        // Leak the error description
-        let error: &'static mut String = Box::leak(Box::new(val.to_string()));
-
-        Self(error.as_str())
+/// ddc-hi wraps every failure in an `anyhow::Error`. The DDC/CI errors underneath
+/// are generic over the I2C implementation, so they're told apart by their message.
+fn ddc_error(error: anyhow::Error, code: Option<u8>) -> MonitorError {
+    if let Some(e) = error.downcast_ref::<io::Error>() {
+        return io::Error::new(e.kind(), e.to_string()).into();
     }
-}
 
-impl From<ParserError> for MonitorError {
-    fn from(val: ParserError) -> Self {
-        // Leak the error description
-        let error: &'static mut String = Box::leak(Box::new(val.to_string()));
+    let message = error.to_string();
 
-        Self(error.as_str())
+    match code {
+        Some(code) if message.contains("Unsupported VCP code") => {
+            MonitorError::UnsupportedFeature { code }
+        }
+        _ if message.starts_with("DDC/CI error") => MonitorError::Protocol { message },
+        _ if message.contains("Permission denied") => MonitorError::PermissionDenied { message },
+        _ if message.contains("timed out") => MonitorError::Timeout,
+        _ => MonitorError::io(message),
     }
 }
 
-impl From<BorrowMutError> for MonitorError {
-    fn from(val: BorrowMutError) -> Self {
-        // Leak the error description
-        let error: &'static mut String = Box::leak(Box::new(val.to_string()));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_set_vcp_requests() {
+        // Brightness to 50 from the host
+        assert_eq!(
+            set_vcp_packet(HOST_SOURCE_ADDRESS, 0x10, 50),
+            [0x51, 0x84, 0x03, 0x10, 0x00, 0x32, 0x9A]
+        );
+
+        // HDMI 1 through 0xF4 from 0x50, the way LG monitors switch inputs
+        assert_eq!(
+            set_vcp_packet(0x50, 0xF4, 0x90),
+            [0x50, 0x84, 0x03, 0xF4, 0x00, 0x90, 0xDD]
+        );
+    }
+
+    #[test]
+    fn checksum_covers_the_destination_address() {
+        let packet = set_vcp_packet(0x50, 0x60, 0x1234);
+        let sum = packet.iter().fold(DDC_CI_ADDRESS << 1, |sum, b| sum ^ b);
 
-        Self(error.as_str())
+        assert_eq!(&packet[3..6], [0x60, 0x12, 0x34]);
+        assert_eq!(sum, 0);
     }
 }
@@ -1,11 +1,164 @@
 use std::{error, fmt};
 
-#[derive(Debug, Clone)]
-pub struct ParserError(&'static str);
+use crate::monitor::features::{self, FeatureDefinition};
+
+/// Number of characters shown on each side of the failure by `ParserError::render`
+const RENDER_CONTEXT: usize = 40;
+
+/// Why and where a capability string couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserError {
+    pub message: &'static str,
+    /// Byte offset into the capability string, if the failure could be located
+    pub offset: Option<usize>,
+    pub token: Option<String>,
+    /// Keys of the lists enclosing the failure, outermost first, e.g. `["vcp", "60"]`
+    pub path: Vec<String>,
+}
+
+impl ParserError {
+    fn new(message: &'static str) -> ParserError {
+        ParserError {
+            message,
+            offset: None,
+            token: None,
+            path: vec![],
+        }
+    }
+
+    /// A failure at one of the tokens of the capability string
+    fn at(message: &'static str, source: &str, token: Token) -> ParserError {
+        ParserError {
+            message,
+            offset: Some(token.offset),
+            token: Some(token.text.to_string()),
+            path: key_path(source, token.offset),
+        }
+    }
+
+    /// A failure because the capability string ended too early
+    fn at_end(message: &'static str, source: &str) -> ParserError {
+        ParserError {
+            message,
+            offset: Some(source.len()),
+            token: None,
+            path: key_path(source, source.len()),
+        }
+    }
+
+    /// A failure found in an already parsed token, placed with `spanned` or
+    /// `locate`
+    pub fn with_token(message: &'static str, token: &str) -> ParserError {
+        ParserError {
+            token: Some(token.to_string()),
+            ..ParserError::new(message)
+        }
+    }
+
+    /// A failure at an already parsed expression
+    fn at_expression(message: &'static str, expression: &Expression) -> ParserError {
+        let token = match expression {
+            Expression::Atom(atom, _) => Some(atom.clone()),
+            Expression::List(..) => None,
+        };
+
+        ParserError {
+            offset: expression.span().0,
+            token,
+            ..ParserError::new(message)
+        }
+    }
+
+    /// Place the failure at the expression it was found in, unless it's placed
+    /// already
+    pub fn spanned(mut self, span: Span) -> ParserError {
+        self.offset = self.offset.or(span.0);
+        self
+    }
+
+    /// Nest the failure inside the list of `key`
+    pub fn within(mut self, key: &str) -> ParserError {
+        self.path.insert(0, key.to_string());
+        self
+    }
+
+    /// Find the offset of a failure raised after parsing without a span, i.e. the
+    /// first token matching the offending one under the same keys
+    pub fn locate(mut self, source: &str) -> ParserError {
+        if self.offset.is_some() {
+            return self;
+        }
+
+        if let Some(token) = &self.token {
+            let path = &self.path;
+
+            walk_keys(&tokenize(source), |t, keys| {
+                let found = t.text == token && keys_match(keys, path);
+                if found {
+                    self.offset = Some(t.offset);
+                }
+
+                found
+            });
+        }
+
+        self
+    }
+
+    /// The capability string around the failure with a caret under it, for error
+    /// reports
+    pub fn render(&self, source: &str) -> String {
+        let offset = match self.offset {
+            Some(offset) => offset,
+            None => return format!("{}\n{}", source, self),
+        };
+
+        let chars: Vec<(usize, char)> = source.char_indices().collect();
+        let at = chars
+            .iter()
+            .position(|(i, _)| *i >= offset)
+            .unwrap_or(chars.len());
+        let start = at.saturating_sub(RENDER_CONTEXT);
+        let end = (at + RENDER_CONTEXT).min(chars.len());
+
+        let mut line = String::new();
+        let mut column = at - start;
+
+        if start > 0 {
+            line.push_str("...");
+            column += 3;
+        }
+
+        line.extend(chars[start..end].iter().map(|(_, c)| match c.is_control() {
+            true => ' ',
+            false => *c,
+        }));
+
+        if end < chars.len() {
+            line.push_str("...");
+        }
+
+        format!("{}\n{}^ {}", line, " ".repeat(column), self.message)
+    }
+}
 
 impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Parser Error: {}", self.0)
+        write!(f, "Parser Error: {}", self.message)?;
+
+        if let Some(token) = &self.token {
+            write!(f, " '{}'", token)?;
+        }
+
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path.join(" > "))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -15,155 +168,977 @@ impl error::Error for ParserError {
     }
 }
 
+/// Byte offset of a parsed expression in its capability string, `None` for
+/// expressions built in code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span(pub Option<usize>);
+
+impl Span {
+    fn at(offset: usize) -> Span {
+        Span(Some(offset))
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Expression {
-    Atom(String),
-    List(Vec<Expression>),
+    Atom(String, Span),
+    List(Vec<Expression>, Span),
 }
 
-fn tokenize(input: &str) -> Vec<&str> {
-    let split: Vec<&str> = input.split(" ").filter(|&s| s != "").collect();
+impl Expression {
+    pub fn atom(atom: &str) -> Expression {
+        Expression::Atom(atom.to_string(), Span::default())
+    }
+
+    pub fn list(list: Vec<Expression>) -> Expression {
+        Expression::List(list, Span::default())
+    }
 
-    return split;
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Atom(_, span) | Expression::List(_, span) => *span,
+        }
+    }
 }
 
-#[allow(dead_code, unused_variables)]
-fn read_from_tokens(tokens: &[&str]) -> Result<Expression, ParserError> {
-    let (_remaining, exp) = read_from_tokens_inner(&tokens)?;
-    return Ok(exp);
+/// Canonical MCCS syntax: atoms separated by a single space and lists directly
+/// after the atom they belong to, e.g. `(10 60(0F 11) E0)`
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Atom(atom, _) => write!(f, "{}", atom),
+            Expression::List(list, _) => {
+                write!(f, "(")?;
 
-    fn read_from_tokens_inner<'a>(
-        tokens: &'a [&'a str],
-    ) -> Result<(&'a [&'a str], Expression), ParserError> {
-        let token = *tokens.first().ok_or(ParserError("unexpected eof"))?;
-        let mut tokens = &tokens[1..];
+                for (i, item) in list.iter().enumerate() {
+                    if i > 0 && matches!(item, Expression::Atom(..)) {
+                        write!(f, " ")?;
+                    }
 
-        return if token == "(" {
-            let mut exp_list: Vec<Expression> = vec![];
+                    write!(f, "{}", item)?;
+                }
 
-            while tokens.first() != Some(&")") {
-                let (remaining, exp) = read_from_tokens_inner(tokens)?;
-                tokens = remaining;
+                write!(f, ")")
+            }
+        }
+    }
+}
 
-                exp_list.push(exp);
+/// Keys whose value is free text, e.g. `model(DELL U2720Q)`, rather than a list of
+/// hex codes
+const TEXT_KEYS: [&str; 7] = [
+    "prot",
+    "type",
+    "model",
+    "mccs_ver",
+    "mswhql",
+    "asset_eep",
+    "mpu",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Token<'a> {
+    text: &'a str,
+    /// Byte offset into the capability string
+    offset: usize,
+}
+
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut word_start = None;
+
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' | ')' => {
+                end_word(&mut tokens, input, word_start.take(), i);
+                tokens.push(Token {
+                    text: &input[i..i + 1],
+                    offset: i,
+                });
+            }
+            c if c.is_whitespace() => end_word(&mut tokens, input, word_start.take(), i),
+            _ => {
+                word_start.get_or_insert(i);
             }
+        }
+    }
 
-            if tokens.first() != Some(&")") {
-                return Err(ParserError("expected )"));
+    end_word(&mut tokens, input, word_start, input.len());
+
+    tokens
+}
+
+fn end_word<'a>(tokens: &mut Vec<Token<'a>>, input: &'a str, start: Option<usize>, end: usize) {
+    if let Some(start) = start {
+        tokens.push(Token {
+            text: &input[start..end],
+            offset: start,
+        });
+    }
+}
+
+/// Visit the tokens along with the keys of the lists enclosing them, keyless lists
+/// as empty keys, until `visit` returns true
+fn walk_keys<'a>(tokens: &[Token<'a>], mut visit: impl FnMut(&Token<'a>, &[&'a str]) -> bool) {
+    let mut keys: Vec<&str> = vec![];
+    let mut last_atom = "";
+
+    for token in tokens {
+        if visit(token, &keys) {
+            return;
+        }
+
+        match token.text {
+            "(" => keys.push(std::mem::take(&mut last_atom)),
+            ")" => {
+                keys.pop();
+                last_atom = "";
             }
+            atom => last_atom = atom,
+        }
+    }
+}
+
+fn keys_match(keys: &[&str], path: &[String]) -> bool {
+    keys.iter().filter(|key| !key.is_empty()).eq(path
+        .iter()
+        .map(|key| key.as_str())
+        .collect::<Vec<_>>()
+        .iter())
+}
+
+/// Keys of the lists enclosing the byte at `offset`
+fn key_path(source: &str, offset: usize) -> Vec<String> {
+    let mut path = vec![];
+
+    walk_keys(&tokenize(source), |token, keys| {
+        path = keys
+            .iter()
+            .filter(|key| !key.is_empty())
+            .map(|key| key.to_string())
+            .collect();
+
+        token.offset >= offset
+    });
+
+    path
+}
+
+/// How strictly a capability string is parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Reject anything that isn't well formed
+    Strict,
+    /// Repair the malformations shipping monitors are known for, recording a
+    /// warning for each repair
+    Lenient,
+}
+
+/// The items of the outer list
+fn read_from_tokens(
+    tokens: &[Token],
+    source: &str,
+    mode: ParseMode,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Expression>, ParserError> {
+    if mode == ParseMode::Strict {
+        let token = tokens
+            .first()
+            .ok_or(ParserError::at_end("unexpected eof", source))?;
 
-            tokens = &tokens[1..];
-            let exp_list = Expression::List(exp_list);
+        return match token.text {
+            "(" => {
+                let (remaining, list) = read_list(&tokens[1..], source, mode, warnings, 1, false)?;
 
-            Ok((tokens, exp_list))
-        } else if token == ")" {
-            Err(ParserError("invalid cap string"))
-        } else {
-            Ok((tokens, Expression::Atom(token.to_string())))
+                match remaining.first() {
+                    Some(token) => Err(ParserError::at(
+                        "data after the closing parenthesis",
+                        source,
+                        *token,
+                    )),
+                    None => Ok(list),
+                }
+            }
+            ")" => Err(ParserError::at("invalid cap string", source, *token)),
+            _ => Err(ParserError::at(
+                "top level expr must be list",
+                source,
+                *token,
+            )),
         };
     }
+
+    let (_remaining, mut items) = read_list(tokens, source, mode, warnings, 0, false)?;
+
+    match items.first() {
+        Some(Expression::List(..)) => {
+            let mut list = match items.remove(0) {
+                Expression::List(list, _) => list,
+                _ => unreachable!(),
+            };
+
+            if !items.is_empty() {
+                warnings.push("found data after the closing parenthesis".to_string());
+                list.extend(items);
+            }
+
+            Ok(list)
+        }
+        _ => {
+            warnings.push("capability string isn't wrapped in parentheses".to_string());
+            Ok(items)
+        }
+    }
 }
 
-fn clean_input(input: String) -> String {
-    return input
-        .replace("(", " ( ")
-        .replace(")", " ) ")
-        .replace("  ", " ");
+/// Read expressions up to the closing parenthesis of the current list, or up to
+/// the end of the tokens for the implicit top level list at depth 0. Nested lists
+/// of a list with `text_children` are read as text, e.g. the names in `vcpname`.
+fn read_list<'a>(
+    mut tokens: &'a [Token<'a>],
+    source: &str,
+    mode: ParseMode,
+    warnings: &mut Vec<String>,
+    depth: usize,
+    text_children: bool,
+) -> Result<(&'a [Token<'a>], Vec<Expression>), ParserError> {
+    let mut exp_list: Vec<Expression> = vec![];
+
+    loop {
+        let token = match tokens.first() {
+            Some(token) => *token,
+            None if depth == 0 => return Ok((tokens, exp_list)),
+            None if mode == ParseMode::Lenient => {
+                warnings.push("missing closing parenthesis".to_string());
+                return Ok((tokens, exp_list));
+            }
+            None => return Err(ParserError::at_end("expected )", source)),
+        };
+
+        tokens = &tokens[1..];
+
+        match token.text {
+            "(" if text_children || (depth <= 1 && is_text_key(exp_list.last())) => {
+                let (remaining, text) = read_text(tokens, source, token.offset, mode, warnings)?;
+                tokens = remaining;
+
+                exp_list.push(Expression::List(text, Span::at(token.offset)));
+            }
+            "(" => {
+                let names = depth <= 1 && is_key(exp_list.last(), "vcpname");
+
+                let (remaining, list) =
+                    read_list(tokens, source, mode, warnings, depth + 1, names)?;
+                tokens = remaining;
+
+                exp_list.push(Expression::List(list, Span::at(token.offset)));
+            }
+            ")" if depth > 0 => return Ok((tokens, exp_list)),
+            ")" => warnings.push("skipped an unbalanced closing parenthesis".to_string()),
+            atom => exp_list.push(Expression::Atom(atom.to_string(), Span::at(token.offset))),
+        }
+    }
 }
 
-pub fn parse_cap_string(cap_string: String) -> Result<Vec<(String, Expression)>, ParserError> {
-    let cleaned = clean_input(cap_string);
-    let tokens = tokenize(&cleaned);
+fn is_text_key(expression: Option<&Expression>) -> bool {
+    TEXT_KEYS.iter().any(|key| is_key(expression, key))
+}
 
-    let list = match read_from_tokens(&tokens)? {
-        Expression::List(list) if list.len() % 2 == 0 => list,
-        _ => return Err(ParserError("top level expr must be list")),
+fn is_key(expression: Option<&Expression>, key: &str) -> bool {
+    match expression {
+        Some(Expression::Atom(atom, _)) => atom == key,
+        _ => false,
+    }
+}
+
+/// Read the value of a text key verbatim up to its closing parenthesis, as a list
+/// holding a single atom (or nothing when the value is empty).
+fn read_text<'a>(
+    mut tokens: &'a [Token<'a>],
+    source: &str,
+    open_offset: usize,
+    mode: ParseMode,
+    warnings: &mut Vec<String>,
+) -> Result<(&'a [Token<'a>], Vec<Expression>), ParserError> {
+    let mut depth = 0;
+
+    let close_offset = loop {
+        let token = match tokens.first() {
+            Some(token) => *token,
+            None if mode == ParseMode::Lenient => {
+                warnings.push("missing closing parenthesis".to_string());
+                break source.len();
+            }
+            None => return Err(ParserError::at_end("expected )", source)),
+        };
+
+        tokens = &tokens[1..];
+
+        match token.text {
+            "(" => depth += 1,
+            ")" if depth == 0 => break token.offset,
+            ")" => depth -= 1,
+            _ => {}
+        }
     };
 
+    let raw = &source[open_offset + 1..close_offset];
+    let text = raw.trim();
+    let offset = open_offset + 1 + raw.len() - raw.trim_start().len();
+
+    match text.is_empty() {
+        true => Ok((tokens, vec![])),
+        false => Ok((
+            tokens,
+            vec![Expression::Atom(text.to_string(), Span::at(offset))],
+        )),
+    }
+}
+
+pub fn parse_cap_string(cap_string: String) -> Result<Vec<(String, Expression)>, ParserError> {
+    let mut warnings = vec![];
+
+    parse_cap_string_with(cap_string, ParseMode::Strict, &mut warnings)
+}
+
+/// Parse a capability string into its top level `key(value)` pairs. In lenient mode
+/// every repair made along the way is added to `warnings`.
+pub fn parse_cap_string_with(
+    cap_string: String,
+    mode: ParseMode,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<(String, Expression)>, ParserError> {
+    let tokens = tokenize(&cap_string);
+
+    let list = read_from_tokens(&tokens, &cap_string, mode, warnings)?;
+
+    if mode == ParseMode::Strict && list.len() % 2 == 1 {
+        let unpaired = unpaired_element(&list).unwrap();
+
+        return Err(ParserError::at_expression(
+            "odd number of top level elements",
+            unpaired,
+        ));
+    }
+
     let mut chunks: Vec<(String, Expression)> = vec![];
 
-    let mut iter = list.into_iter();
+    let mut iter = list.into_iter().peekable();
     while let Some(key) = iter.next() {
-        let key = match key {
-            Expression::Atom(k) => k,
-            _ => return Err(ParserError("key isn't an atom")),
+        let (key, key_span) = match (key, mode) {
+            (Expression::Atom(key, span), _) => (key, span),
+            (Expression::List(..), ParseMode::Lenient) => {
+                warnings.push("skipped a value without a key".to_string());
+                continue;
+            }
+            (Expression::List(_, span), ParseMode::Strict) => {
+                return Err(ParserError::new("key isn't an atom").spanned(span))
+            }
         };
 
-        let value = iter.next().ok_or(ParserError("key without value"))?;
+        let value = match (iter.peek(), mode) {
+            (Some(Expression::List(..)), _) | (Some(_), ParseMode::Strict) => iter.next().unwrap(),
+            (_, ParseMode::Lenient) => {
+                warnings.push(format!("skipped key {} without a value", key));
+                continue;
+            }
+            (None, ParseMode::Strict) => {
+                return Err(ParserError::with_token("key without value", &key).spanned(key_span))
+            }
+        };
 
         let pair = (key, value);
 
         chunks.push(pair);
     }
 
-    return Ok(chunks);
+    if mode == ParseMode::Lenient && chunks.is_empty() {
+        return Err(ParserError::new("no capabilities found"));
+    }
+
+    Ok(chunks)
 }
 
-#[derive(Default, Debug, Clone)]
+/// The top level element most likely to throw off the pairing of keys and values,
+/// the first key followed by another atom instead of its value
+fn unpaired_element(list: &[Expression]) -> Option<&Expression> {
+    list.windows(2)
+        .find(|pair| matches!(pair, [Expression::Atom(..), Expression::Atom(..)]))
+        .map(|pair| &pair[0])
+        .or(list.last())
+}
+
+/// Write `key(value)` pairs back into a capability string, e.g.
+/// `(prot(monitor)type(LCD)vcp(10 60(0F 11)))`.
+///
+/// Whatever `parse_cap_string` returns comes back unchanged when the output is
+/// parsed again. Other expressions only do when their atoms have no whitespace or
+/// parentheses, outside of text values such as `model`.
+pub fn to_cap_string(pairs: &[(String, Expression)]) -> String {
+    let mut cap_string = String::from("(");
+    let mut after_atom = false;
+
+    for (key, value) in pairs {
+        // A key straight after an atom value would run into it
+        if after_atom {
+            cap_string.push(' ');
+        }
+
+        match value {
+            Expression::Atom(..) => cap_string.push_str(&format!("{} {}", key, value)),
+            Expression::List(..) => cap_string.push_str(&format!("{}{}", key, value)),
+        }
+
+        after_atom = matches!(value, Expression::Atom(..));
+    }
+
+    cap_string.push(')');
+    cap_string
+}
+
+/// A VCP feature from the `vcp` section and the values it's listed with, e.g.
+/// `60(0F 11)`. Features listed without values take any value.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct VCPCommand {
-    pub command: String,
-    pub values: Vec<VCPCommand>,
+    pub code: u8,
+    pub values: Vec<u16>,
+    /// What MCCS defines the feature as, `None` for manufacturer specific ones
+    pub feature: Option<&'static FeatureDefinition>,
 }
 
-pub fn extract_atom(expression: Expression) -> String {
-    match expression {
-        Expression::List(list) => {
-            let first = list.first().unwrap();
+impl VCPCommand {
+    /// The listed values that fit in a byte, i.e. all of them for features with a
+    /// fixed set of values
+    pub fn byte_values(&self) -> impl Iterator<Item = u8> + '_ {
+        self.values
+            .iter()
+            .filter_map(|value| u8::try_from(*value).ok())
+    }
+}
 
-            return match first {
-                Expression::Atom(value) => value.to_string(),
-                _ => Default::default(),
-            };
+fn strip_hex_prefix(token: &str) -> &str {
+    token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+        .unwrap_or(token)
+}
+
+fn is_hex(token: &str) -> bool {
+    !token.is_empty() && token.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// A VCP feature code the way monitors write them, in either case, with or without
+/// padding or a `0x` prefix
+pub fn parse_code(token: &str) -> Result<u8, ParserError> {
+    let hex = strip_hex_prefix(token);
+
+    match is_hex(hex) {
+        true => u8::from_str_radix(hex, 16)
+            .map_err(|_| ParserError::with_token("VCP code out of range", token)),
+        false => Err(ParserError::with_token("invalid VCP code", token)),
+    }
+}
+
+/// A VCP feature code in canonical form, two uppercase hex digits
+pub fn format_code(code: u8) -> String {
+    format!("{:02X}", code)
+}
+
+/// A VCP value in canonical form, two or four uppercase hex digits
+pub fn format_value(value: u16) -> String {
+    match value {
+        0..=0xFF => format!("{:02X}", value),
+        _ => format!("{:04X}", value),
+    }
+}
+
+/// A VCP value from a value list, written like a code but up to two bytes wide
+pub fn parse_value(token: &str) -> Result<u16, ParserError> {
+    let hex = strip_hex_prefix(token);
+
+    match is_hex(hex) {
+        true => u16::from_str_radix(hex, 16)
+            .map_err(|_| ParserError::with_token("VCP value out of range", token)),
+        false => Err(ParserError::with_token("invalid VCP value", token)),
+    }
+}
+
+/// Fail in strict mode, skip the offending part with a warning in lenient mode
+pub fn skip_invalid(
+    mode: ParseMode,
+    warnings: &mut Vec<String>,
+    error: ParserError,
+) -> Result<(), ParserError> {
+    match mode {
+        ParseMode::Strict => Err(error),
+        ParseMode::Lenient => {
+            match &error.token {
+                Some(token) => warnings.push(format!("skipped {} '{}'", error.message, token)),
+                None => warnings.push(format!("skipped {}", error.message)),
+            }
+
+            Ok(())
         }
+    }
+}
+
+pub fn extract_atom(expression: Expression) -> String {
+    match expression {
+        Expression::List(list, _) => match list.first() {
+            Some(Expression::Atom(value, _)) => value.to_string(),
+            _ => Default::default(),
+        },
         _ => Default::default(),
     }
 }
 
-pub fn extract_vcp_commands(expression: Expression) -> Vec<VCPCommand> {
+/// The `vcp` (or `cmds`) section, codes optionally followed by their value list
+pub fn extract_vcp_commands(
+    expression: Expression,
+    mode: ParseMode,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<VCPCommand>, ParserError> {
+    let list = match expression {
+        Expression::List(list, _) => list,
+        Expression::Atom(..) => return Ok(vec![]),
+    };
+
     let mut cmds: Vec<VCPCommand> = vec![];
+    let mut iter = list.into_iter().peekable();
 
-    match expression {
-        Expression::List(list) => {
-            let mut iter = list.into_iter().peekable();
-
-            while let Some(e) = iter.next() {
-                let mut new_cmd = VCPCommand {
-                    command: Default::default(),
-                    values: vec![],
-                };
-
-                match e {
-                    Expression::Atom(inner_value) => {
-                        if let Some(next) = iter.peek() {
-                            match next {
-                                Expression::List(list) => {
-                                    for result in list {
-                                        match result {
-                                            Expression::Atom(value) => {
-                                                let cmd = VCPCommand {
-                                                    command: value.to_string(),
-                                                    values: vec![],
-                                                };
-
-                                                new_cmd.values.push(cmd);
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                        new_cmd.command = inner_value;
-                    }
-                    _ => {}
+    while let Some(e) = iter.next() {
+        let (token, span) = match e {
+            Expression::Atom(token, span) => (token, span),
+            Expression::List(_, span) => {
+                let error = ParserError::new("value list without a code").spanned(span);
+                skip_invalid(mode, warnings, error)?;
+                continue;
+            }
+        };
+
+        let values = match iter.peek() {
+            Some(Expression::List(..)) => match iter.next() {
+                Some(Expression::List(values, _)) => values,
+                _ => unreachable!(),
+            },
+            _ => vec![],
+        };
+
+        let code = match parse_code(&token) {
+            Ok(code) => code,
+            Err(e) => {
+                skip_invalid(mode, warnings, e.spanned(span))?;
+                continue;
+            }
+        };
+
+        let mut cmd = VCPCommand {
+            code,
+            values: vec![],
+            feature: features::lookup(code),
+        };
+
+        for value in values {
+            let (value, span) = match value {
+                Expression::Atom(value, span) => (value, span),
+                Expression::List(_, span) => {
+                    let error = ParserError::new("nested value list")
+                        .within(&token)
+                        .spanned(span);
+                    skip_invalid(mode, warnings, error)?;
+                    continue;
                 }
+            };
 
-                cmds.push(new_cmd);
+            match parse_value(&value) {
+                Ok(value) => cmd.values.push(value),
+                Err(e) => skip_invalid(mode, warnings, e.within(&token).spanned(span))?,
             }
         }
-        _ => Default::default(),
+
+        cmds.push(cmd);
+    }
+
+    Ok(cmds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::capabilities::MonitorCapabilities;
+
+    fn lenient(cap_string: &str) -> MonitorCapabilities {
+        MonitorCapabilities::from_cap_string_with(cap_string.to_string(), ParseMode::Lenient)
+            .unwrap()
+    }
+
+    fn vcp_codes(caps: &MonitorCapabilities) -> Vec<u8> {
+        caps.vcp_codes.iter().map(|cmd| cmd.code).collect()
+    }
+
+    fn atom(text: &str) -> Expression {
+        Expression::atom(text)
+    }
+
+    fn list(items: Vec<Expression>) -> Expression {
+        Expression::list(items)
+    }
+
+    /// Spans differ between a parsed and a built or re-serialized expression
+    fn without_spans(expression: &Expression) -> Expression {
+        match expression {
+            Expression::Atom(text, _) => atom(text),
+            Expression::List(items, _) => list(items.iter().map(without_spans).collect()),
+        }
+    }
+
+    fn pairs_without_spans(pairs: &[(String, Expression)]) -> Vec<(String, Expression)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.clone(), without_spans(value)))
+            .collect()
+    }
+
+    #[test]
+    fn lenient_repairs_missing_outer_list() {
+        let cap_string = "prot(monitor)type(lcd)model(ABC)vcp(10 12 60(0F 11))mccs_ver(2.1)";
+        let caps = lenient(cap_string);
+
+        assert_eq!(caps.display_model, "ABC");
+        assert_eq!(vcp_codes(&caps), [0x10, 0x12, 0x60]);
+        assert_eq!(caps.vcp_feature(0x60).unwrap().values, [0x0F, 0x11]);
+        assert_eq!(
+            caps.warnings,
+            ["capability string isn't wrapped in parentheses"]
+        );
+
+        assert!(parse_cap_string(cap_string.to_string()).is_err());
     }
 
-    return cmds;
+    #[test]
+    fn lenient_repairs_missing_closing_parenthesis() {
+        let cap_string = "(prot(monitor)type(lcd)model(ABC)vcp(10 12 60(0F 11)";
+        let caps = lenient(cap_string);
+
+        assert_eq!(caps.display_model, "ABC");
+        assert_eq!(vcp_codes(&caps), [0x10, 0x12, 0x60]);
+        assert_eq!(
+            caps.warnings,
+            ["missing closing parenthesis", "missing closing parenthesis"]
+        );
+
+        assert!(parse_cap_string(cap_string.to_string()).is_err());
+    }
+
+    #[test]
+    fn lenient_repairs_odd_element_count() {
+        let cap_string =
+            "(prot(monitor) stray type(lcd)model(ABC)vcp(10 60(0F 11)) (xx) mccs_ver(2.1))";
+        let caps = lenient(cap_string);
+
+        assert_eq!(caps.display_model, "ABC");
+        assert_eq!(caps.display_type, "lcd");
+        assert_eq!(vcp_codes(&caps), [0x10, 0x60]);
+        assert_eq!(caps.mccs_version, "2.1");
+        assert_eq!(
+            caps.warnings,
+            [
+                "skipped key stray without a value",
+                "skipped a value without a key"
+            ]
+        );
+
+        assert!(parse_cap_string(cap_string.to_string()).is_err());
+    }
+
+    #[test]
+    fn lenient_repairs_trailing_garbage() {
+        let cap_string = "(prot(monitor)type(lcd))model(ABC)vcp(10 60(0F 11))garbage";
+        let caps = lenient(cap_string);
+
+        assert_eq!(caps.display_model, "ABC");
+        assert_eq!(vcp_codes(&caps), [0x10, 0x60]);
+        assert_eq!(
+            caps.warnings,
+            [
+                "found data after the closing parenthesis",
+                "skipped key garbage without a value"
+            ]
+        );
+
+        let error = parse_cap_string(cap_string.to_string()).unwrap_err();
+        assert_eq!(error.message, "data after the closing parenthesis");
+        assert_eq!(error.token.as_deref(), Some("model"));
+    }
+
+    #[test]
+    fn lenient_skips_unbalanced_closing_parenthesis() {
+        let caps = lenient("(prot(monitor)model(ABC)vcp(10 60(0F 11))))");
+
+        assert_eq!(caps.display_model, "ABC");
+        assert_eq!(vcp_codes(&caps), [0x10, 0x60]);
+        assert_eq!(caps.warnings, ["skipped an unbalanced closing parenthesis"]);
+    }
+
+    #[test]
+    fn lenient_fails_without_any_capabilities() {
+        let result =
+            MonitorCapabilities::from_cap_string_with("garbage".to_string(), ParseMode::Lenient);
+
+        assert_eq!(result.unwrap_err().message, "no capabilities found");
+    }
+
+    #[test]
+    fn text_fields_keep_their_full_text() {
+        let cap_string = "(prot(monitor)type(LCD)model(DELL U2720Q)vcp(10 60(0F 11))mswhql(1)asset_eep(40 A1)mccs_ver(2.1))";
+        let pairs = pairs_without_spans(&parse_cap_string(cap_string.to_string()).unwrap());
+
+        assert_eq!(
+            pairs[2],
+            ("model".to_string(), list(vec![atom("DELL U2720Q")]))
+        );
+        assert_eq!(
+            pairs[3],
+            (
+                "vcp".to_string(),
+                list(vec![
+                    atom("10"),
+                    atom("60"),
+                    list(vec![atom("0F"), atom("11")])
+                ])
+            )
+        );
+
+        let caps = MonitorCapabilities::from_cap_string(cap_string.to_string()).unwrap();
+
+        assert_eq!(caps.display_model, "DELL U2720Q");
+        assert_eq!(caps.display_type, "LCD");
+        assert_eq!(caps.mswhql, Some(true));
+        assert_eq!(caps.asset_eep.as_deref(), Some("40 A1"));
+        assert_eq!(caps.mccs_version, "2.1");
+    }
+
+    #[test]
+    fn text_fields_keep_parentheses_and_trim_whitespace() {
+        let caps = MonitorCapabilities::from_cap_string(
+            "(prot(monitor)model(  U2720Q (rev A)  )vcp(10))".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(caps.display_model, "U2720Q (rev A)");
+        assert_eq!(caps.vcp_codes.len(), 1);
+    }
+
+    #[test]
+    fn empty_text_fields() {
+        let caps = MonitorCapabilities::from_cap_string(
+            "(prot(monitor)model()asset_eep()vcp(10))".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(caps.display_model, "");
+        assert_eq!(caps.asset_eep.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn parses_codes_in_any_case_and_padding() {
+        assert_eq!(parse_code("0F"), Ok(0x0F));
+        assert_eq!(parse_code("f"), Ok(0x0F));
+        assert_eq!(parse_code("0x0f"), Ok(0x0F));
+        assert_eq!(parse_code("0XE0"), Ok(0xE0));
+        assert_eq!(
+            parse_code("100").unwrap_err().message,
+            "VCP code out of range"
+        );
+        assert_eq!(parse_code("zz").unwrap_err().message, "invalid VCP code");
+        assert_eq!(parse_code("0x").unwrap_err().message, "invalid VCP code");
+
+        assert_eq!(parse_value("0100"), Ok(0x100));
+        assert_eq!(parse_value("FFFF"), Ok(0xFFFF));
+        assert_eq!(
+            parse_value("10000").unwrap_err().message,
+            "VCP value out of range"
+        );
+
+        assert_eq!(format_code(0x0F), "0F");
+        assert_eq!(format_value(0x0F), "0F");
+        assert_eq!(format_value(0x100), "0100");
+    }
+
+    #[test]
+    fn vcp_section_is_typed() {
+        let caps = MonitorCapabilities::from_cap_string(
+            "(prot(monitor)vcp(10 60(f 0x11 1b) e0(0100 FFFF))cmds(01 f3))".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(vcp_codes(&caps), [0x10, 0x60, 0xE0]);
+        assert!(caps.vcp_feature(0x10).unwrap().values.is_empty());
+        assert_eq!(caps.vcp_feature(0x60).unwrap().values, [0x0F, 0x11, 0x1B]);
+        assert_eq!(caps.vcp_feature(0xE0).unwrap().values, [0x100, 0xFFFF]);
+        assert_eq!(caps.commands, [0x01, 0xF3]);
+
+        assert_eq!(
+            caps.vcp_feature(0x60).unwrap().feature.map(|f| f.code),
+            Some(0x60)
+        );
+        assert!(caps.vcp_feature(0xE0).unwrap().feature.is_none());
+    }
+
+    #[test]
+    fn invalid_codes_fail_strictly_and_are_skipped_leniently() {
+        let cap_string = "(prot(monitor)vcp(10 60(0F zz 11) qq(01) 12))";
+
+        let error = MonitorCapabilities::from_cap_string(cap_string.to_string()).unwrap_err();
+        assert_eq!(error.message, "invalid VCP value");
+        assert_eq!(error.token.as_deref(), Some("zz"));
+        assert_eq!(error.path, ["vcp", "60"]);
+
+        let caps = lenient(cap_string);
+        assert_eq!(vcp_codes(&caps), [0x10, 0x60, 0x12]);
+        assert_eq!(caps.vcp_feature(0x60).unwrap().values, [0x0F, 0x11]);
+        assert_eq!(
+            caps.warnings,
+            [
+                "skipped invalid VCP value 'zz'",
+                "skipped invalid VCP code 'qq'"
+            ]
+        );
+    }
+
+    fn strict_error(cap_string: &str) -> ParserError {
+        MonitorCapabilities::from_cap_string(cap_string.to_string()).unwrap_err()
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_token() {
+        let cap_string = "(prot(monitor)type(LCD)vcp(10 60(0F zz 11) E0)mccs_ver(2.1))";
+        let error = strict_error(cap_string);
+
+        assert_eq!(error.offset, Some(cap_string.find("zz").unwrap()));
+        assert_eq!(error.token.as_deref(), Some("zz"));
+        assert_eq!(error.path, ["vcp", "60"]);
+        assert_eq!(
+            error.to_string(),
+            "Parser Error: invalid VCP value 'zz' at byte 36 in vcp > 60"
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_repeated_token_that_failed() {
+        // The same token parses fine as a value, only the code is out of range
+        let cap_string = "(prot(monitor)vcp(10 60(0100) 0100))";
+        let error = strict_error(cap_string);
+
+        assert_eq!(error.message, "VCP code out of range");
+        assert_eq!(error.offset, Some(cap_string.rfind("0100").unwrap()));
+    }
+
+    #[test]
+    fn errors_at_the_end_of_the_string() {
+        let cap_string = "(prot(monitor)type(LCD)vcp(10 60(0F 11) E0";
+        let error = strict_error(cap_string);
+
+        assert_eq!(error.message, "expected )");
+        assert_eq!(error.offset, Some(cap_string.len()));
+        assert_eq!(error.path, ["vcp"]);
+    }
+
+    #[test]
+    fn errors_found_after_parsing_are_located() {
+        let cap_string = "(prot(monitor) stray type(LCD) vcp(10))";
+        let error = strict_error(cap_string);
+        assert_eq!(error.message, "odd number of top level elements");
+        assert_eq!(error.token.as_deref(), Some("stray"));
+        assert_eq!(error.offset, Some(cap_string.find("stray").unwrap()));
+
+        let cap_string = "(prot(monitor)(LCD)(x)vcp(10))";
+        let error = strict_error(cap_string);
+        assert_eq!(error.message, "key isn't an atom");
+        assert_eq!(error.offset, Some(cap_string.find("(LCD)").unwrap()));
+
+        let cap_string = "(prot(monitor)vcp((0F) 10))";
+        let error = strict_error(cap_string);
+        assert_eq!(error.message, "value list without a code");
+        assert_eq!(error.offset, Some(cap_string.find("(0F)").unwrap()));
+        assert_eq!(error.path, ["vcp"]);
+
+        let cap_string = "(prot(monitor)vcp(60(0F (11))))";
+        let error = strict_error(cap_string);
+        assert_eq!(error.message, "nested value list");
+        assert_eq!(error.offset, Some(cap_string.find("(11)").unwrap()));
+        assert_eq!(error.path, ["vcp", "60"]);
+    }
+
+    #[test]
+    fn renders_a_caret_under_the_failure() {
+        let cap_string = "(prot(monitor)vcp(10 60(0F zz 11)))";
+        let rendered = strict_error(cap_string).render(cap_string);
+
+        assert_eq!(
+            rendered,
+            "(prot(monitor)vcp(10 60(0F zz 11)))\n                           ^ invalid VCP value"
+        );
+    }
+
+    #[test]
+    fn renders_long_strings_around_the_failure() {
+        let cap_string = format!("(prot(monitor)vcp({} zz))", "10 ".repeat(40));
+        let rendered = strict_error(&cap_string).render(&cap_string);
+        let (line, caret) = rendered.split_once('\n').unwrap();
+
+        assert!(line.starts_with("..."));
+        assert!(line.ends_with("zz))"));
+        assert_eq!(&line[caret.find('^').unwrap()..][..2], "zz");
+    }
+
+    /// Parse, serialize and parse again, the second parse has to match the first
+    fn round_trip(cap_string: &str) -> String {
+        let pairs = parse_cap_string(cap_string.to_string()).unwrap();
+        let serialized = to_cap_string(&pairs);
+
+        assert_eq!(
+            pairs_without_spans(&parse_cap_string(serialized.clone()).unwrap()),
+            pairs_without_spans(&pairs)
+        );
+
+        serialized
+    }
+
+    #[test]
+    fn round_trips_atom_values() {
+        assert_eq!(round_trip("(a b c d)"), "(a b c d)");
+        assert_eq!(round_trip("(a b vcp(10) c d)"), "(a b vcp(10)c d)");
+    }
+
+    #[test]
+    fn round_trips_nested_lists() {
+        assert_eq!(
+            round_trip("( prot(monitor) vcp( 10 60( 0f 11 ) e0(01(02)) ) )"),
+            "(prot(monitor)vcp(10 60(0f 11) e0(01(02))))"
+        );
+    }
+
+    #[test]
+    fn round_trips_text_sections() {
+        let cap_string = "(prot(monitor)type(LCD)model(DELL U2720Q)vcp(10 60(0F 11 1B) E0)vcpname(E0(Hot Key) 60(Input Source(DP HDMI USB-C)) 10())window(window1(type(PIP) area(25 25 1895 1175) max(640 480) min(10 10)) window2())asset_eep()mpu()mswhql(1)mccs_ver(2.1))";
+
+        assert_eq!(round_trip(cap_string), cap_string);
+    }
+
+    #[test]
+    fn round_trips_capabilities() {
+        let cap_string = "(prot(monitor)type(lcd)model(VG248)cmds(01 02 03 07 0C F3)vcp(02 04 10 12 14(05 06 08 0B) 60(01 03 11 0F) 62 8D(01 02) E0(0100))vcpname(60(Input Source(VGA DVI HDMI DisplayPort)) E0(Hot Key))window(window1(type(PIP) area(25 25 1895 1175) max(640 480) min(10 10)))mccs_ver(2.1)asset_eep()mpu()mswhql(1))";
+        let caps = MonitorCapabilities::from_cap_string(cap_string.to_string()).unwrap();
+
+        let canonical = caps.to_cap_string();
+        let parsed = MonitorCapabilities::from_cap_string(canonical.clone()).unwrap();
+
+        assert_eq!(parsed, caps);
+        assert_eq!(parsed.to_cap_string(), canonical);
+        assert_eq!(parsed.asset_eep.as_deref(), Some(""));
+        assert_eq!(parsed.mpu_version.as_deref(), Some(""));
+        assert_eq!(parsed.windows[0].max_size, Some((640, 480)));
+        assert_eq!(parsed.value_name(0x60, 0x11), Some("HDMI"),);
+    }
 }
@@ -1,6 +1,15 @@
+pub mod backend;
 pub mod capabilities;
+pub mod controls;
+pub mod edid;
+pub mod emulator;
+pub mod features;
+pub mod identity;
+pub mod info;
 pub mod input;
 pub mod mccs;
+pub mod power;
+pub mod quirks;
 
 #[cfg(target_os = "windows")]
 mod windows;
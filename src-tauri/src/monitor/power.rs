@@ -0,0 +1,68 @@
+use std::{thread, time::Duration};
+
+use crate::{
+    errors::MonitorError,
+    monitor::{backend::ControlledDisplay, input::MonitorInput},
+};
+
+/// How long a monitor takes to come out of standby before it accepts an input
+/// switch
+const WAKE_DELAY: Duration = Duration::from_millis(2000);
+
+/// DPM power state, as read from and written to VCP feature 0xD6
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerMode {
+    On,
+    Standby,
+    Suspend,
+    Off,
+}
+
+impl PowerMode {
+    pub fn code(&self) -> u8 {
+        match self {
+            PowerMode::On => 0x01,
+            PowerMode::Standby => 0x02,
+            PowerMode::Suspend => 0x03,
+            PowerMode::Off => 0x04,
+        }
+    }
+
+    /// 0x05 is the power button being switched off, which can't be written
+    pub fn from_code(code: u8) -> Option<PowerMode> {
+        match code {
+            0x01 => Some(PowerMode::On),
+            0x02 => Some(PowerMode::Standby),
+            0x03 => Some(PowerMode::Suspend),
+            0x04 | 0x05 => Some(PowerMode::Off),
+            _ => None,
+        }
+    }
+}
+
+/// Turn the monitor on when it's in a power saving mode and wait for it to come
+/// out of standby. Monitors that can't report their power mode are left alone.
+pub fn wake(display: &dyn ControlledDisplay) -> Result<(), MonitorError> {
+    if let Ok(mode) = display.get_power_mode() {
+        if mode != PowerMode::On {
+            display.set_power_mode(PowerMode::On)?;
+            thread::sleep(WAKE_DELAY);
+        }
+    }
+
+    Ok(())
+}
+
+/// Switch input, first waking the monitor when `wake` is set
+pub fn switch_input(
+    display: &dyn ControlledDisplay,
+    input: MonitorInput,
+    wake: bool,
+) -> Result<(), MonitorError> {
+    if wake {
+        self::wake(display)?;
+    }
+
+    display.set_input(input)
+}
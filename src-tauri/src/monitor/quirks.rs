@@ -0,0 +1,480 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    errors::MonitorError,
+    monitor::{
+        backend::{ControlledDisplay, HOST_SOURCE_ADDRESS},
+        capabilities::MonitorCapabilities,
+        edid::Edid,
+        identity::MonitorId,
+        input::MonitorInput,
+    },
+};
+
+const BUNDLED_QUIRKS: &str = include_str!("quirks.json");
+
+/// The bundle identifier from tauri.conf.json, which names the app's config directory
+const APP_IDENTIFIER: &str = "ell.dev.shortmon";
+
+/// `quirks.json` next to the GUI's settings, in the app's config directory
+pub fn user_quirks_path() -> Option<PathBuf> {
+    tauri::api::path::config_dir().map(|dir| dir.join(APP_IDENTIFIER).join("quirks.json"))
+}
+
+/// Corrections for a monitor model whose DDC/CI implementation doesn't match what
+/// its capability string claims. Every match field that is set has to match.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Quirk {
+    pub manufacturer_id: Option<String>,
+    pub product_code: Option<u16>,
+    /// Capability string model, or the EDID monitor name when there is none
+    pub model: Option<String>,
+    /// Input codes to write instead of the ones the capability string reports
+    #[serde(default)]
+    pub input_map: HashMap<MonitorInput, MonitorInput>,
+    /// Inputs to list instead of the ones from the capability string
+    pub inputs: Option<Vec<MonitorInput>>,
+    /// VCP feature to write input switches to instead of 0x60
+    pub input_feature: Option<u8>,
+    /// DDC/CI source address to switch inputs from instead of the host's
+    pub source_address: Option<u8>,
+    /// Time the monitor needs after a write before it accepts the next request
+    pub write_delay_ms: Option<u64>,
+    /// VCP features the monitor lists but doesn't implement properly
+    #[serde(default)]
+    pub broken_features: Vec<u8>,
+}
+
+impl Quirk {
+    fn matches(&self, edid: Option<&Edid>, model: Option<&str>) -> bool {
+        let manufacturer = edid.map(|e| e.manufacturer_id.as_str());
+        let product_code = edid.map(|e| e.product_code);
+
+        let manufacturer_ok = match &self.manufacturer_id {
+            Some(wanted) => matches!(manufacturer, Some(m) if m.eq_ignore_ascii_case(wanted)),
+            None => true,
+        };
+
+        let product_ok = match self.product_code {
+            Some(wanted) => product_code == Some(wanted),
+            None => true,
+        };
+
+        let model_ok = match &self.model {
+            Some(wanted) => matches!(model, Some(m) if m.trim().eq_ignore_ascii_case(wanted)),
+            None => true,
+        };
+
+        let has_key =
+            self.manufacturer_id.is_some() || self.product_code.is_some() || self.model.is_some();
+
+        has_key && manufacturer_ok && product_ok && model_ok
+    }
+
+    /// Fill in whatever this quirk doesn't set from a lower priority one
+    fn merge(&mut self, other: &Quirk) {
+        for (reported, written) in &other.input_map {
+            self.input_map.entry(*reported).or_insert(*written);
+        }
+
+        if self.inputs.is_none() {
+            self.inputs = other.inputs.clone();
+        }
+
+        if self.input_feature.is_none() {
+            self.input_feature = other.input_feature;
+        }
+
+        if self.source_address.is_none() {
+            self.source_address = other.source_address;
+        }
+
+        if self.write_delay_ms.is_none() {
+            self.write_delay_ms = other.write_delay_ms;
+        }
+
+        for code in &other.broken_features {
+            if !self.broken_features.contains(code) {
+                self.broken_features.push(*code);
+            }
+        }
+    }
+}
+
+/// The quirks shipped with shortmon plus any the user adds. User entries are
+/// looked up first, so they override the bundled ones.
+#[derive(Debug, Clone, Default)]
+pub struct QuirkDatabase {
+    quirks: Vec<Quirk>,
+}
+
+impl QuirkDatabase {
+    pub fn bundled() -> QuirkDatabase {
+        QuirkDatabase {
+            quirks: serde_json::from_str(BUNDLED_QUIRKS).expect("bundled quirks are invalid"),
+        }
+    }
+
+    /// Add the quirks from a JSON file. A missing file isn't an error.
+    pub fn load_file(&mut self, path: &Path) -> Result<(), MonitorError> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => {
+                return Err(MonitorError::io(format!(
+                    "Unable to read quirks from {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+
+        let quirks: Vec<Quirk> = serde_json::from_str(&json).map_err(|e| {
+            MonitorError::io(format!("Invalid quirks in {}: {}", path.display(), e))
+        })?;
+
+        self.quirks.splice(0..0, quirks);
+
+        Ok(())
+    }
+
+    /// Add the user's quirks, from the app's config directory and then from the file
+    /// `SHORTMON_QUIRKS` points to, which takes priority. Shared by the GUI and the
+    /// CLI so a monitor behaves the same in both. Returns the files that couldn't be
+    /// loaded.
+    pub fn load_user_files(&mut self) -> Vec<MonitorError> {
+        let paths = user_quirks_path()
+            .into_iter()
+            .chain(std::env::var_os("SHORTMON_QUIRKS").map(PathBuf::from));
+
+        paths
+            .filter_map(|path| self.load_file(&path).err())
+            .collect()
+    }
+
+    /// Every quirk matching the monitor, merged into one
+    pub fn lookup(&self, edid: Option<&Edid>, model: Option<&str>) -> Option<Quirk> {
+        self.quirks
+            .iter()
+            .filter(|quirk| quirk.matches(edid, model))
+            .fold(None, |merged: Option<Quirk>, quirk| match merged {
+                Some(mut merged) => {
+                    merged.merge(quirk);
+                    Some(merged)
+                }
+                None => Some(quirk.clone()),
+            })
+    }
+
+    /// Wrap a display in its quirks, if it has any
+    pub fn apply(&self, display: Box<dyn ControlledDisplay>) -> Box<dyn ControlledDisplay> {
+        let model = display
+            .capabilities()
+            .map(|c| c.display_model.clone())
+            .filter(|model| !model.is_empty())
+            .or_else(|| display.edid().and_then(|e| e.monitor_name.clone()));
+
+        match self.lookup(display.edid(), model.as_deref()) {
+            Some(quirk) => Box::new(QuirkedDisplay { display, quirk }),
+            None => display,
+        }
+    }
+}
+
+/// A display with its model's quirks applied on top
+pub struct QuirkedDisplay {
+    display: Box<dyn ControlledDisplay>,
+    quirk: Quirk,
+}
+
+impl QuirkedDisplay {
+    fn check_feature(&self, code: u8) -> Result<(), MonitorError> {
+        match self.quirk.broken_features.contains(&code) {
+            true => Err(MonitorError::UnsupportedFeature { code }),
+            false => Ok(()),
+        }
+    }
+
+    fn wait_after_write(&self) {
+        if let Some(delay) = self.quirk.write_delay_ms {
+            thread::sleep(Duration::from_millis(delay));
+        }
+    }
+}
+
+impl ControlledDisplay for QuirkedDisplay {
+    fn id(&self) -> &MonitorId {
+        self.display.id()
+    }
+
+    fn capabilities(&self) -> Option<&MonitorCapabilities> {
+        self.display.capabilities()
+    }
+
+    fn capabilities_error(&self) -> Option<&MonitorError> {
+        self.display.capabilities_error()
+    }
+
+    fn edid(&self) -> Option<&Edid> {
+        self.display.edid()
+    }
+
+    fn get_inputs(&self) -> Result<Vec<MonitorInput>, MonitorError> {
+        match &self.quirk.inputs {
+            Some(inputs) => Ok(inputs.clone()),
+            None => self.display.get_inputs(),
+        }
+    }
+
+    fn get_vcp_feature(&self, code: u8) -> Result<(u16, u16), MonitorError> {
+        self.check_feature(code)?;
+        self.display.get_vcp_feature(code)
+    }
+
+    fn set_vcp_feature(&self, code: u8, value: u16) -> Result<(), MonitorError> {
+        self.check_feature(code)?;
+
+        let result = self.display.set_vcp_feature(code, value);
+        self.wait_after_write();
+
+        result
+    }
+
+    /// Read back through the feature inputs are switched with, since switches
+    /// written there don't always change how 0x60 reads. Monitors that can't
+    /// read it fall back to 0x60.
+    fn get_current_input(&self) -> Result<MonitorInput, MonitorError> {
+        let current = match self.quirk.input_feature {
+            Some(code) => self
+                .get_vcp_feature(code)
+                .map(|(value, _)| MonitorInput::from((value & 0xff) as u8))
+                .or_else(|_| self.display.get_current_input())?,
+            None => self.display.get_current_input()?,
+        };
+
+        // Report the input the way it's listed rather than the written code
+        Ok(self
+            .quirk
+            .input_map
+            .iter()
+            .find(|(_, written)| **written == current)
+            .map(|(reported, _)| *reported)
+            .unwrap_or(current))
+    }
+
+    fn set_input(&self, input: MonitorInput) -> Result<(), MonitorError> {
+        let mapped = self.quirk.input_map.get(&input).copied().unwrap_or(input);
+        let code = self.quirk.input_feature.unwrap_or(0x60);
+        let source_address = self.quirk.source_address.unwrap_or(HOST_SOURCE_ADDRESS);
+
+        self.check_feature(code)?;
+
+        let result =
+            match self
+                .display
+                .set_vcp_feature_from(source_address, code, mapped.code() as u16)
+            {
+                // The input map and feature only work from their source address, switch
+                // the standard way on platforms that can't write from it
+                Err(MonitorError::UnsupportedSourceAddress { .. }) => self.display.set_input(input),
+                result => result,
+            };
+        self.wait_after_write();
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::{
+        backend::{BackendRegistry, DisplayBackend},
+        emulator::EmulatorBackend,
+    };
+
+    fn demo_edids() -> (Edid, Edid) {
+        let displays = EmulatorBackend::demo().enumerate().unwrap();
+
+        (
+            displays[0].edid().unwrap().clone(),
+            displays[1].edid().unwrap().clone(),
+        )
+    }
+
+    fn with_user_file(json: &str) -> QuirkDatabase {
+        let path = std::env::temp_dir().join(format!("shortmon-quirks-{}.json", json.len()));
+        fs::write(&path, json).unwrap();
+
+        let mut quirks = QuirkDatabase::bundled();
+        let result = quirks.load_file(&path);
+        fs::remove_file(&path).ok();
+        result.unwrap();
+
+        quirks
+    }
+
+    /// Delegates to an emulated monitor but, like the Windows backend, can only
+    /// write from the host address
+    struct HostOnlyDisplay(Box<dyn ControlledDisplay>);
+
+    impl ControlledDisplay for HostOnlyDisplay {
+        fn id(&self) -> &MonitorId {
+            self.0.id()
+        }
+
+        fn capabilities(&self) -> Option<&MonitorCapabilities> {
+            self.0.capabilities()
+        }
+
+        fn capabilities_error(&self) -> Option<&MonitorError> {
+            self.0.capabilities_error()
+        }
+
+        fn edid(&self) -> Option<&Edid> {
+            self.0.edid()
+        }
+
+        fn get_inputs(&self) -> Result<Vec<MonitorInput>, MonitorError> {
+            self.0.get_inputs()
+        }
+
+        fn get_vcp_feature(&self, code: u8) -> Result<(u16, u16), MonitorError> {
+            self.0.get_vcp_feature(code)
+        }
+
+        fn set_vcp_feature(&self, code: u8, value: u16) -> Result<(), MonitorError> {
+            self.0.set_vcp_feature(code, value)
+        }
+    }
+
+    #[test]
+    fn missing_file_adds_nothing() {
+        let mut quirks = QuirkDatabase::default();
+
+        assert!(quirks
+            .load_file(Path::new("/nonexistent/quirks.json"))
+            .is_ok());
+    }
+
+    #[test]
+    fn invalid_file_is_reported() {
+        let path = std::env::temp_dir().join("shortmon-invalid-quirks.json");
+        fs::write(&path, "[{\"model\": 27}]").unwrap();
+
+        let result = QuirkDatabase::default().load_file(&path);
+        fs::remove_file(&path).ok();
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("Invalid quirks in"), "{}", message);
+    }
+
+    #[test]
+    fn reads_input_back_through_the_input_feature() {
+        let mut registry = BackendRegistry::default();
+        *registry.quirks_mut() = QuirkDatabase::bundled();
+        registry.register(EmulatorBackend::demo());
+
+        let monitors = registry.enumerate().displays;
+        let lg = &monitors[1].display;
+
+        assert_eq!(lg.get_current_input(), Ok(MonitorInput::HDMI1));
+
+        lg.set_input(MonitorInput::DisplayPort2).unwrap();
+
+        assert_eq!(lg.get_vcp_feature(0xF4), Ok((0xD1, 0xFF)));
+        assert_eq!(lg.get_current_input(), Ok(MonitorInput::DisplayPort2));
+    }
+
+    #[test]
+    fn matches_manufacturer_and_product_code() {
+        let (dell, lg) = demo_edids();
+
+        let quirk = Quirk {
+            manufacturer_id: Some("gsm".to_string()),
+            ..Default::default()
+        };
+        assert!(quirk.matches(Some(&lg), None));
+        assert!(!quirk.matches(Some(&dell), None));
+        assert!(!quirk.matches(None, Some("27GL850")));
+
+        let quirk = Quirk {
+            manufacturer_id: Some("GSM".to_string()),
+            product_code: Some(0x5B7F),
+            ..Default::default()
+        };
+        assert!(quirk.matches(Some(&lg), None));
+
+        let quirk = Quirk {
+            manufacturer_id: Some("GSM".to_string()),
+            product_code: Some(0x1234),
+            ..Default::default()
+        };
+        assert!(!quirk.matches(Some(&lg), None));
+    }
+
+    #[test]
+    fn matches_model() {
+        let (_, lg) = demo_edids();
+
+        let quirk = Quirk {
+            model: Some("27gl850".to_string()),
+            ..Default::default()
+        };
+        assert!(quirk.matches(None, Some(" 27GL850 ")));
+        assert!(quirk.matches(Some(&lg), Some("27GL850")));
+        assert!(!quirk.matches(Some(&lg), Some("27GL83A")));
+        assert!(!quirk.matches(Some(&lg), None));
+    }
+
+    #[test]
+    fn needs_something_to_match_on() {
+        let (_, lg) = demo_edids();
+
+        let quirk = Quirk {
+            write_delay_ms: Some(100),
+            ..Default::default()
+        };
+        assert!(!quirk.matches(Some(&lg), Some("27GL850")));
+    }
+
+    #[test]
+    fn user_entries_override_bundled_ones() {
+        let (_, lg) = demo_edids();
+        let quirks = with_user_file(
+            r#"[{"manufacturer_id": "GSM", "write_delay_ms": 5, "input_map": {"HDMI1": "0x92"}}]"#,
+        );
+
+        let quirk = quirks.lookup(Some(&lg), Some("27GL850")).unwrap();
+
+        assert_eq!(quirk.write_delay_ms, Some(5));
+        assert_eq!(
+            quirk.input_map[&MonitorInput::HDMI1],
+            MonitorInput::Other(0x92)
+        );
+        // Whatever the user entry doesn't set still comes from the bundled one
+        assert_eq!(
+            quirk.input_map[&MonitorInput::HDMI2],
+            MonitorInput::Other(0x91)
+        );
+        assert_eq!(quirk.input_feature, Some(0xF4));
+        assert_eq!(quirk.source_address, Some(0x50));
+    }
+
+    #[test]
+    fn switches_through_0x60_without_source_address_support() {
+        let lg = EmulatorBackend::demo().enumerate().unwrap().remove(1);
+        let lg = QuirkDatabase::bundled().apply(Box::new(HostOnlyDisplay(lg)));
+
+        lg.set_input(MonitorInput::HDMI2).unwrap();
+
+        assert_eq!(lg.get_vcp_feature(0x60), Ok((0x12, 0x12)));
+        assert_eq!(lg.get_current_input(), Ok(MonitorInput::HDMI2));
+    }
+}
@@ -1,18 +1,35 @@
 use std::fmt;
 use std::os::windows::raw::HANDLE;
+
+use widestring::{U16CStr, U16CString};
 use winapi::shared::minwindef::{BYTE, DWORD, LPARAM, LPDWORD};
 use winapi::shared::windef::{HDC, HMONITOR, LPRECT};
+use winapi::shared::winerror::{
+    ERROR_GRAPHICS_DDCCI_INVALID_DATA, ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_CHECKSUM,
+    ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_COMMAND, ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_LENGTH,
+    ERROR_GRAPHICS_DDCCI_VCP_NOT_SUPPORTED, ERROR_SEM_TIMEOUT,
+};
 use winapi::um::lowlevelmonitorconfigurationapi::{
-    CapabilitiesRequestAndCapabilitiesReply, GetCapabilitiesStringLength, SetVCPFeature,
+    CapabilitiesRequestAndCapabilitiesReply, GetCapabilitiesStringLength,
+    GetVCPFeatureAndVCPFeatureReply, SetVCPFeature, MC_VCP_CODE_TYPE,
 };
 use winapi::um::physicalmonitorenumerationapi::{
     GetNumberOfPhysicalMonitorsFromHMONITOR, GetPhysicalMonitorsFromHMONITOR, PHYSICAL_MONITOR,
 };
-use winapi::um::winuser::EnumDisplayMonitors;
+use winapi::um::wingdi::DISPLAY_DEVICEW;
+use winapi::um::winnt::PVOID;
+use winapi::um::winreg::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY};
+use winapi::um::winuser::{
+    EnumDisplayDevicesW, EnumDisplayMonitors, GetMonitorInfoW, EDD_GET_DEVICE_INTERFACE_NAME,
+    LPMONITORINFO, MONITORINFOEXW,
+};
 
 use crate::errors::MonitorError;
+use crate::monitor::backend::ControlledDisplay;
 use crate::monitor::capabilities::MonitorCapabilities;
-use crate::monitor::input::{get_all_inputs_from_capabilities_string, MonitorInput};
+use crate::monitor::edid::Edid;
+use crate::monitor::identity::{dedup_ids, MonitorId};
+use crate::monitor::input::{inputs_from_capabilities, MonitorInput};
 
 pub unsafe fn set_vcp_feature(
     hmonitor: HANDLE,
@@ -21,26 +38,64 @@ pub unsafe fn set_vcp_feature(
 ) -> Result<(), MonitorError> {
     let result = SetVCPFeature(hmonitor, code, new_value);
 
-    return match result {
+    match result {
         1 => Ok(()),
-        _ => Err(MonitorError("Failed to set value for monitor")),
-    };
+        _ => Err(last_vcp_error(code)),
+    }
+}
+
+pub unsafe fn get_vcp_feature(hmonitor: HANDLE, code: BYTE) -> Result<(u32, u32), MonitorError> {
+    let mut code_type: MC_VCP_CODE_TYPE = 0;
+    let mut current_value: DWORD = 0;
+    let mut maximum_value: DWORD = 0;
+
+    let result = GetVCPFeatureAndVCPFeatureReply(
+        hmonitor,
+        code,
+        &mut code_type,
+        &mut current_value,
+        &mut maximum_value,
+    );
+
+    match result {
+        1 => Ok((current_value, maximum_value)),
+        _ => Err(last_vcp_error(code)),
+    }
+}
+
+/// Map the error left behind by a failed monitor configuration call
+fn last_vcp_error(code: BYTE) -> MonitorError {
+    let error = std::io::Error::last_os_error();
+
+    match error.raw_os_error() {
+        Some(ERROR_GRAPHICS_DDCCI_VCP_NOT_SUPPORTED) => MonitorError::UnsupportedFeature { code },
+        Some(
+            ERROR_GRAPHICS_DDCCI_INVALID_DATA
+            | ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_CHECKSUM
+            | ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_COMMAND
+            | ERROR_GRAPHICS_DDCCI_INVALID_MESSAGE_LENGTH,
+        ) => MonitorError::Protocol {
+            message: error.to_string(),
+        },
+        Some(code) if code == ERROR_SEM_TIMEOUT as i32 => MonitorError::Timeout,
+        _ => error.into(),
+    }
 }
 
 pub unsafe fn enum_display_monitors() -> Vec<HMONITOR> {
     let hdc = std::ptr::null_mut();
     let lprc_clip = std::ptr::null_mut();
 
-    let mut monitors: Box<Vec<HMONITOR>> = Box::new(vec![]);
+    let mut monitors: Box<Vec<HMONITOR>> = Box::default();
 
     let mons_ptr = Box::into_raw(monitors);
-    let mons_lparam: LPARAM = std::mem::transmute(mons_ptr);
+    let mons_lparam = mons_ptr as LPARAM;
 
     EnumDisplayMonitors(hdc, lprc_clip, Some(lpfn_enum_callback), mons_lparam);
 
     monitors = Box::from_raw(mons_ptr);
 
-    return monitors.to_vec();
+    monitors.to_vec()
 }
 
 pub unsafe fn get_number_of_physical_monitors_from_hmonitor(hmonitor: HMONITOR) -> i32 {
@@ -53,7 +108,7 @@ pub unsafe fn get_number_of_physical_monitors_from_hmonitor(hmonitor: HMONITOR)
 
     num_phys_monitors = Box::from_raw(num_ptr);
 
-    return *num_phys_monitors;
+    *num_phys_monitors
 }
 
 pub unsafe fn get_physical_monitors_from_hmonitor(
@@ -64,7 +119,7 @@ pub unsafe fn get_physical_monitors_from_hmonitor(
 
     GetPhysicalMonitorsFromHMONITOR(monitor, phys_mons.len() as u32, phys_mons.as_mut_ptr());
 
-    return phys_mons;
+    phys_mons
 }
 
 pub unsafe fn get_capabilities_string_length(phys_mon: PHYSICAL_MONITOR) -> i32 {
@@ -77,24 +132,108 @@ pub unsafe fn get_capabilities_string_length(phys_mon: PHYSICAL_MONITOR) -> i32
 
     cap_string_len = Box::from_raw(cap_len_ptr);
 
-    return *cap_string_len;
+    *cap_string_len
 }
 
 pub unsafe fn capabilities_request_and_capabilities_reply(
     phys_mon: PHYSICAL_MONITOR,
     cap_string_len: i32,
-) -> Result<String, MonitorError> {
+) -> Result<Vec<u8>, MonitorError> {
     let mut cap_string_buf: Vec<i8> = vec![0; cap_string_len as usize];
 
-    CapabilitiesRequestAndCapabilitiesReply(
+    let result = CapabilitiesRequestAndCapabilitiesReply(
         phys_mon.hPhysicalMonitor,
         cap_string_buf.as_mut_ptr(),
         cap_string_len as u32,
     );
 
-    String::from_utf8(cap_string_buf.iter().map(|&c| c as u8).collect())
-        .map_err(|_| MonitorError("Unable to build cap string from buffer"))
-        .map(|cap_string| String::from(cap_string.trim_matches(char::from(0))))
+    match result {
+        1 => Ok(cap_string_buf.iter().map(|&c| c as u8).collect()),
+        _ => Err(std::io::Error::last_os_error().into()),
+    }
+}
+
+/// Returns the device interface path of the `index`th physical monitor attached to
+/// `hmonitor`, e.g. `\\?\DISPLAY#DEL40F5#5&2b5c0f2a&0&UID4353#{e6f07b5f-...}`.
+pub unsafe fn get_monitor_device_id(hmonitor: HMONITOR, index: u32) -> Option<String> {
+    let mut monitor_info = MONITORINFOEXW {
+        cbSize: std::mem::size_of::<MONITORINFOEXW>() as DWORD,
+        ..Default::default()
+    };
+
+    let result = GetMonitorInfoW(
+        hmonitor,
+        &mut monitor_info as *mut MONITORINFOEXW as LPMONITORINFO,
+    );
+
+    if result == 0 {
+        return None;
+    }
+
+    let mut display_device = DISPLAY_DEVICEW {
+        cb: std::mem::size_of::<DISPLAY_DEVICEW>() as DWORD,
+        ..Default::default()
+    };
+
+    let result = EnumDisplayDevicesW(
+        monitor_info.szDevice.as_ptr(),
+        index,
+        &mut display_device,
+        EDD_GET_DEVICE_INTERFACE_NAME,
+    );
+
+    if result == 0 {
+        return None;
+    }
+
+    U16CStr::from_slice_truncate(&display_device.DeviceID)
+        .ok()
+        .map(|id| id.to_string_lossy())
+        .filter(|id| !id.is_empty())
+}
+
+/// Splits a device interface path into its hardware id (EDID manufacturer + product
+/// code) and instance segments, e.g. `DEL40F5` and `5&2b5c0f2a&0&UID4353`. Both stay
+/// the same for a monitor on a given port.
+fn split_device_id(device_id: &str) -> Option<(&str, &str)> {
+    let segments: Vec<&str> = device_id.split('#').collect();
+
+    match segments.as_slice() {
+        [_, hardware_id, instance, ..] => Some((hardware_id, instance)),
+        _ => None,
+    }
+}
+
+/// Windows caches the EDID of every monitor it has seen in the registry, under
+/// the monitor's device instance.
+pub unsafe fn read_edid_from_registry(hardware_id: &str, instance: &str) -> Option<Vec<u8>> {
+    let subkey = U16CString::from_str(format!(
+        "SYSTEM\\CurrentControlSet\\Enum\\DISPLAY\\{}\\{}\\Device Parameters",
+        hardware_id, instance
+    ))
+    .ok()?;
+    let value = U16CString::from_str("EDID").ok()?;
+
+    let mut data: Vec<u8> = vec![0; 1024];
+    let mut data_len = data.len() as DWORD;
+
+    let result = RegGetValueW(
+        HKEY_LOCAL_MACHINE,
+        subkey.as_ptr(),
+        value.as_ptr(),
+        RRF_RT_REG_BINARY,
+        std::ptr::null_mut(),
+        data.as_mut_ptr() as PVOID,
+        &mut data_len,
+    );
+
+    if result != 0 {
+        return None;
+    }
+
+    data.truncate(data_len as usize);
+
+    Some(data)
 }
 
 unsafe extern "system" fn lpfn_enum_callback(
@@ -103,46 +242,31 @@ unsafe extern "system" fn lpfn_enum_callback(
     _lprect: LPRECT,
     lparam: LPARAM,
 ) -> i32 {
-    let mons_ptr: *mut Vec<HMONITOR> = std::mem::transmute(lparam);
+    let mons_ptr = lparam as *mut Vec<HMONITOR>;
     let mons_ref = &mut *mons_ptr;
 
     mons_ref.push(hmon);
 
-    return 1;
+    1
 }
 
 #[derive(Default, Clone)]
 pub struct Monitor {
-    pub id: u8,
-    pub cap_string: Option<String>,
+    pub id: MonitorId,
     pub capabilities: Option<MonitorCapabilities>,
+    pub capabilities_error: Option<MonitorError>,
+    pub edid: Option<Edid>,
     pub phys_mons: PHYSICAL_MONITOR,
     pub inputs: Vec<MonitorInput>,
 }
 
 impl fmt::Display for Monitor {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<{}>", "Monitor")
+        write!(f, "<Monitor>")
     }
 }
 
 impl Monitor {
-    pub fn set_input(&self, input: MonitorInput) -> Result<(), MonitorError> {
-        let hmonitor = self.phys_mons.hPhysicalMonitor;
-
-        let code = 0x60; // Input Select VCP Code
-        let input_code = input as u32;
-
-        unsafe {
-            set_vcp_feature(hmonitor, code, input_code)
-                .map_err(|_| MonitorError("Failed to set VCP feature"))
-        }
-    }
-
-    pub fn get_inputs(&self) -> Result<Vec<MonitorInput>, MonitorError> {
-        Ok(self.inputs.clone())
-    }
-
     pub fn get_all_monitors() -> Result<Vec<Monitor>, MonitorError> {
         unsafe {
             let display_mons = enum_display_monitors();
@@ -153,45 +277,91 @@ impl Monitor {
                 let phys_num = get_number_of_physical_monitors_from_hmonitor(*mon_ref);
                 let phys_mons = get_physical_monitors_from_hmonitor(*mon_ref, phys_num);
 
-                for phys_mon in phys_mons {
+                for (j, phys_mon) in phys_mons.into_iter().enumerate() {
+                    let device_id = get_monitor_device_id(*mon_ref, j as u32);
+                    let device_path = device_id.as_deref().and_then(split_device_id);
+
+                    let edid = device_path
+                        .and_then(|(hardware_id, instance)| {
+                            read_edid_from_registry(hardware_id, instance)
+                        })
+                        .and_then(|data| Edid::parse(&data).ok());
+
+                    let id = edid
+                        .as_ref()
+                        .and_then(MonitorId::from_edid)
+                        .unwrap_or_else(|| {
+                            let path = device_path
+                                .map(|(hardware_id, instance)| {
+                                    format!("{}#{}", hardware_id, instance)
+                                })
+                                .unwrap_or(format!("{}.{}", i, j));
+
+                            MonitorId::from_bus_path(&path)
+                        });
+
                     let mut mon = Monitor {
-                        id: i as u8,
+                        id,
+                        edid,
                         ..Default::default()
                     };
 
                     let cap_str_len = get_capabilities_string_length(phys_mon);
 
-                    let cap_reply_str =
-                        capabilities_request_and_capabilities_reply(phys_mon, cap_str_len)
-                            .unwrap_or("".to_string());
-
-                    if cap_reply_str.is_empty() || cap_reply_str.eq("") {
-                        continue;
-                    }
-
-                    mon.cap_string = Some(cap_reply_str.clone());
-
-                    let caps = MonitorCapabilities::from_cap_string(cap_reply_str);
-                    match caps {
-                        Ok(result) => {
-                            mon.phys_mons = phys_mon;
+                    mon.phys_mons = phys_mon;
 
-                            if let Ok(inputs) = get_all_inputs_from_capabilities_string(&result) {
-                                mon.inputs = inputs;
-                            } else {
-                                mon.inputs = vec![];
-                            }
+                    let (capabilities, capabilities_error, inputs) = inputs_from_capabilities(
+                        capabilities_request_and_capabilities_reply(phys_mon, cap_str_len)
+                            .and_then(MonitorCapabilities::from_reply),
+                    );
 
-                            mon.capabilities = Some(result);
+                    mon.capabilities = capabilities;
+                    mon.capabilities_error = capabilities_error;
+                    mon.inputs = inputs;
 
-                            monitors.push(mon);
-                        }
-                        Err(_) => {}
-                    }
+                    monitors.push(mon);
                 }
             }
 
+            dedup_ids(monitors.iter_mut().map(|m| &mut m.id));
+
             Ok(monitors)
         }
     }
 }
+
+impl ControlledDisplay for Monitor {
+    fn id(&self) -> &MonitorId {
+        &self.id
+    }
+
+    fn capabilities(&self) -> Option<&MonitorCapabilities> {
+        self.capabilities.as_ref()
+    }
+
+    fn capabilities_error(&self) -> Option<&MonitorError> {
+        self.capabilities_error.as_ref()
+    }
+
+    fn edid(&self) -> Option<&Edid> {
+        self.edid.as_ref()
+    }
+
+    fn get_vcp_feature(&self, code: u8) -> Result<(u16, u16), MonitorError> {
+        let hmonitor = self.phys_mons.hPhysicalMonitor;
+
+        let (current_value, maximum_value) = unsafe { get_vcp_feature(hmonitor, code)? };
+
+        Ok((current_value as u16, maximum_value as u16))
+    }
+
+    fn set_vcp_feature(&self, code: u8, value: u16) -> Result<(), MonitorError> {
+        let hmonitor = self.phys_mons.hPhysicalMonitor;
+
+        unsafe { set_vcp_feature(hmonitor, code, value as u32) }
+    }
+
+    fn get_inputs(&self) -> Result<Vec<MonitorInput>, MonitorError> {
+        Ok(self.inputs.clone())
+    }
+}
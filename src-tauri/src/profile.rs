@@ -0,0 +1,151 @@
+use crate::{
+    errors::MonitorError,
+    monitor::{backend::EnumeratedDisplay, identity::MonitorId, input::MonitorInput, power},
+};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct VcpWrite {
+    pub code: u8,
+    pub value: u16,
+}
+
+/// Switches one monitor to an input, after writing any extra VCP features.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ProfileStep {
+    pub monitor: MonitorId,
+    pub input: MonitorInput,
+    #[serde(default)]
+    pub vcp_writes: Vec<VcpWrite>,
+}
+
+/// A named set of input switches applied together, e.g. moving every monitor on
+/// a desk over to another machine.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub steps: Vec<ProfileStep>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StepResult {
+    pub monitor: MonitorId,
+    pub input: MonitorInput,
+    pub error: Option<MonitorError>,
+}
+
+impl Profile {
+    /// Apply every step in order. A failing step doesn't stop the rest of the
+    /// profile from being applied.
+    pub fn apply(&self, monitors: &[EnumeratedDisplay], wake: bool) -> Vec<StepResult> {
+        self.steps
+            .iter()
+            .map(|step| StepResult {
+                monitor: step.monitor.clone(),
+                input: step.input,
+                error: step.apply(monitors, wake).err(),
+            })
+            .collect()
+    }
+}
+
+impl ProfileStep {
+    fn apply(&self, monitors: &[EnumeratedDisplay], wake: bool) -> Result<(), MonitorError> {
+        let monitor = monitors
+            .iter()
+            .find(|m| *m.display.id() == self.monitor)
+            .ok_or(MonitorError::MonitorNotFound {
+                id: self.monitor.clone(),
+            })?;
+
+        // Wake the monitor first, writes to a monitor in standby go nowhere
+        if wake {
+            power::wake(monitor.display.as_ref())?;
+        }
+
+        for write in &self.vcp_writes {
+            monitor.display.set_vcp_feature(write.code, write.value)?;
+        }
+
+        monitor.display.set_input(self.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::{backend::BackendRegistry, emulator::EmulatorBackend, power::PowerMode};
+
+    fn demo_monitors() -> Vec<EnumeratedDisplay> {
+        let mut registry = BackendRegistry::default();
+        registry.register(EmulatorBackend::demo());
+
+        registry.enumerate().displays
+    }
+
+    #[test]
+    fn applies_every_step() {
+        let monitors = demo_monitors();
+        let dell = monitors[0].display.id().clone();
+        let missing = MonitorId::from_bus_path("missing");
+
+        let profile = Profile {
+            name: "Laptop".to_string(),
+            steps: vec![
+                ProfileStep {
+                    monitor: missing.clone(),
+                    input: MonitorInput::HDMI1,
+                    vcp_writes: vec![],
+                },
+                ProfileStep {
+                    monitor: dell.clone(),
+                    input: MonitorInput::UsbC1,
+                    vcp_writes: vec![VcpWrite {
+                        code: 0x10,
+                        value: 30,
+                    }],
+                },
+            ],
+        };
+
+        let results = profile.apply(&monitors, false);
+
+        assert_eq!(
+            results[0].error,
+            Some(MonitorError::MonitorNotFound { id: missing })
+        );
+        assert_eq!(results[1].error, None);
+
+        let display = &monitors[0].display;
+        assert_eq!(display.get_current_input(), Ok(MonitorInput::UsbC1));
+        assert_eq!(display.get_vcp_feature(0x10), Ok((30, 100)));
+    }
+
+    #[test]
+    fn wakes_before_writing() {
+        let monitors = demo_monitors();
+        let display = &monitors[0].display;
+        display.set_power_mode(PowerMode::Standby).unwrap();
+
+        let profile = Profile {
+            name: "Desk".to_string(),
+            steps: vec![ProfileStep {
+                monitor: display.id().clone(),
+                input: MonitorInput::HDMI1,
+                vcp_writes: vec![VcpWrite {
+                    code: 0x10,
+                    value: 30,
+                }],
+            }],
+        };
+
+        assert_eq!(
+            profile.apply(&monitors, false)[0].error,
+            Some(MonitorError::Timeout)
+        );
+        assert_eq!(profile.apply(&monitors, true)[0].error, None);
+
+        assert_eq!(display.get_power_mode(), Ok(PowerMode::On));
+        assert_eq!(display.get_current_input(), Ok(MonitorInput::HDMI1));
+        assert_eq!(display.get_vcp_feature(0x10), Ok((30, 100)));
+    }
+}
@@ -0,0 +1,193 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    profile::Profile,
+    shortcut::{Shortcut, ShortcutAction},
+};
+
+/// User settings, persisted as JSON in the app's config directory.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    #[serde(default)]
+    pub shortcuts: Vec<Shortcut>,
+    /// Turn monitors in standby on before switching their input
+    #[serde(default)]
+    pub wake_on_switch: bool,
+}
+
+impl Settings {
+    /// A missing file gives the defaults, one that can't be read or parsed is an
+    /// error so it isn't saved over.
+    pub fn load(path: &Path) -> io::Result<Settings> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => return Err(e),
+        };
+
+        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        fs::write(path, json)
+    }
+
+    pub fn find_profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn create_profile(&mut self, profile: Profile) -> Result<(), String> {
+        if profile.name.trim().is_empty() {
+            return Err("Profile name can't be empty".to_string());
+        }
+
+        if self.find_profile(&profile.name).is_some() {
+            return Err(format!("Profile {} already exists", profile.name));
+        }
+
+        self.profiles.push(profile);
+
+        Ok(())
+    }
+
+    /// Replace a profile, pointing the shortcuts that apply it to its new name
+    pub fn edit_profile(&mut self, name: &str, profile: Profile) -> Result<(), String> {
+        if profile.name.trim().is_empty() {
+            return Err("Profile name can't be empty".to_string());
+        }
+
+        if profile.name != name && self.find_profile(&profile.name).is_some() {
+            return Err(format!("Profile {} already exists", profile.name));
+        }
+
+        let existing = self
+            .profiles
+            .iter_mut()
+            .find(|p| p.name == name)
+            .ok_or(format!("Profile {} not found", name))?;
+
+        for shortcut in &mut self.shortcuts {
+            if let ShortcutAction::ApplyProfile { profile: applied } = &mut shortcut.action {
+                if applied == name {
+                    *applied = profile.name.clone();
+                }
+            }
+        }
+
+        *existing = profile;
+
+        Ok(())
+    }
+
+    /// Delete a profile, unless a shortcut applies it
+    pub fn delete_profile(&mut self, name: &str) -> Result<(), String> {
+        if self.find_profile(name).is_none() {
+            return Err(format!("Profile {} not found", name));
+        }
+
+        let used_by = self.shortcuts.iter().find(|shortcut| {
+            matches!(&shortcut.action, ShortcutAction::ApplyProfile { profile } if profile == name)
+        });
+
+        if let Some(shortcut) = used_by {
+            return Err(format!(
+                "Profile {} is applied by the shortcut {}",
+                name, shortcut.accelerator
+            ));
+        }
+
+        self.profiles.retain(|p| p.name != name);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_file_gives_defaults() {
+        let settings = Settings::load(Path::new("/nonexistent/settings.json")).unwrap();
+
+        assert!(settings.profiles.is_empty());
+        assert!(!settings.wake_on_switch);
+    }
+
+    #[test]
+    fn invalid_file_is_an_error() {
+        let path = std::env::temp_dir().join("shortmon-invalid-settings.json");
+        fs::write(&path, "{\"profiles\": [").unwrap();
+
+        let result = Settings::load(&path);
+        fs::remove_file(&path).ok();
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn profile(name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            steps: vec![],
+        }
+    }
+
+    fn with_shortcut(profile: &str) -> Settings {
+        Settings {
+            profiles: vec![self::profile(profile)],
+            shortcuts: vec![Shortcut {
+                accelerator: "Ctrl+Alt+1".to_string(),
+                action: ShortcutAction::ApplyProfile {
+                    profile: profile.to_string(),
+                },
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn refuses_duplicate_and_empty_profile_names() {
+        let mut settings = with_shortcut("Desk");
+
+        assert!(settings.create_profile(profile("Desk")).is_err());
+        assert!(settings.create_profile(profile(" ")).is_err());
+        assert!(settings.create_profile(profile("Laptop")).is_ok());
+        assert!(settings.edit_profile("Laptop", profile("Desk")).is_err());
+    }
+
+    #[test]
+    fn renaming_a_profile_updates_its_shortcuts() {
+        let mut settings = with_shortcut("Desk");
+
+        settings.edit_profile("Desk", profile("Office")).unwrap();
+
+        assert!(settings.find_profile("Office").is_some());
+        assert!(matches!(
+            &settings.shortcuts[0].action,
+            ShortcutAction::ApplyProfile { profile } if profile == "Office"
+        ));
+    }
+
+    #[test]
+    fn refuses_deleting_profiles_applied_by_shortcuts() {
+        let mut settings = with_shortcut("Desk");
+        settings.create_profile(profile("Laptop")).unwrap();
+
+        let error = settings.delete_profile("Desk").unwrap_err();
+        assert!(error.contains("Ctrl+Alt+1"), "{}", error);
+        assert!(settings.find_profile("Desk").is_some());
+
+        settings.delete_profile("Laptop").unwrap();
+        assert!(settings.find_profile("Laptop").is_none());
+        assert!(settings.delete_profile("Laptop").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::monitor::{identity::MonitorId, input::MonitorInput};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum ShortcutAction {
+    SwitchInput {
+        monitor: MonitorId,
+        input: MonitorInput,
+    },
+    ApplyProfile {
+        profile: String,
+    },
+    /// Switch the monitor to the input after the current one in its input list
+    CycleInput {
+        monitor: MonitorId,
+    },
+}
+
+/// A global keyboard shortcut, e.g. `CmdOrCtrl+Shift+1`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Shortcut {
+    pub accelerator: String,
+    pub action: ShortcutAction,
+}
+
+const MODIFIERS: &[&str] = &[
+    "OPTION",
+    "ALT",
+    "CONTROL",
+    "CTRL",
+    "COMMAND",
+    "CMD",
+    "SUPER",
+    "SHIFT",
+    "COMMANDORCONTROL",
+    "COMMANDORCTRL",
+    "CMDORCTRL",
+    "CMDORCONTROL",
+];
+
+const NAMED_KEYS: &[&str] = &[
+    "`",
+    "BACKQUOTE",
+    "BACKSLASH",
+    "\\",
+    "[",
+    "BRACKETLEFT",
+    "]",
+    "BRACKETRIGHT",
+    ",",
+    "COMMA",
+    "=",
+    "-",
+    ".",
+    "PERIOD",
+    "'",
+    "QUOTE",
+    ";",
+    "SEMICOLON",
+    "/",
+    "SLASH",
+    "BACKSPACE",
+    "CAPSLOCK",
+    "CONTEXTMENU",
+    "ENTER",
+    "SPACE",
+    "TAB",
+    "CONVERT",
+    "DELETE",
+    "END",
+    "HELP",
+    "HOME",
+    "PAGEDOWN",
+    "PAGEUP",
+    "DOWN",
+    "UP",
+    "LEFT",
+    "RIGHT",
+    "NUMLOCK",
+    "NUMADD",
+    "NUMPADADD",
+    "NUMBACKSPACE",
+    "NUMPADBACKSPACE",
+    "NUMCLEAR",
+    "NUMPADCLEAR",
+    "NUMCOMMA",
+    "NUMPADCOMMA",
+    "NUMDIVIDE",
+    "NUMPADDIVIDE",
+    "NUMSUBSTRACT",
+    "NUMPADSUBSTRACT",
+    "NUMENTER",
+    "NUMPADENTER",
+    "ESC",
+    "ESCAPE",
+    "FN",
+    "FNLOCK",
+    "PRINTSCREEN",
+    "SCROLLLOCK",
+    "PAUSE",
+    "VOLUMEMUTE",
+    "VOLUMEDOWN",
+    "VOLUMEUP",
+    "MEDIANEXTTRACK",
+    "MEDIAPREVIOUSTRACK",
+    "MEDIAPLAYPAUSE",
+    "LAUNCHMAIL",
+    "SUSPEND",
+];
+
+/// The same modifier goes by several names, e.g. `CTRL` and `CONTROL`
+fn canonical_modifier(modifier: &str) -> &str {
+    match modifier {
+        "OPTION" => "ALT",
+        "CONTROL" => "CTRL",
+        "COMMAND" | "SUPER" => "CMD",
+        "COMMANDORCONTROL" | "COMMANDORCTRL" | "CMDORCONTROL" => "CMDORCTRL",
+        modifier => modifier,
+    }
+}
+
+fn is_key(token: &str) -> bool {
+    let is_char = token.len() == 1 && token.chars().all(|c| c.is_ascii_alphanumeric());
+
+    let is_numpad = ["NUM", "NUMPAD"].iter().any(|prefix| {
+        matches!(
+            token.strip_prefix(prefix),
+            Some(n) if n.len() == 1 && n.chars().all(|c| c.is_ascii_digit())
+        )
+    });
+
+    let is_function = matches!(
+        token.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()),
+        Some(n) if (1..=35).contains(&n)
+    );
+
+    is_char || is_numpad || is_function || NAMED_KEYS.contains(&token)
+}
+
+/// Check an accelerator is made of modifiers followed by a single key. The
+/// shortcut manager panics on accelerators it can't parse, so user supplied ones
+/// have to be checked first.
+pub fn validate_accelerator(accelerator: &str) -> Result<(), String> {
+    let tokens: Vec<String> = accelerator
+        .to_uppercase()
+        .split('+')
+        .map(|t| t.trim().to_string())
+        .collect();
+
+    let (key, modifiers) = tokens
+        .split_last()
+        .ok_or(format!("Invalid shortcut {}", accelerator))?;
+
+    if tokens.iter().any(|t| t.is_empty()) {
+        return Err(format!("Invalid shortcut {}", accelerator));
+    }
+
+    if let Some(modifier) = modifiers.iter().find(|m| !MODIFIERS.contains(&m.as_str())) {
+        return Err(format!("Invalid modifier {} in {}", modifier, accelerator));
+    }
+
+    for (i, modifier) in modifiers.iter().enumerate() {
+        let canonical = canonical_modifier(modifier);
+
+        if modifiers[..i]
+            .iter()
+            .any(|m| canonical_modifier(m) == canonical)
+        {
+            return Err(format!(
+                "Duplicate modifier {} in {}",
+                modifier, accelerator
+            ));
+        }
+    }
+
+    if !is_key(key) {
+        return Err(format!("Invalid key {} in {}", key, accelerator));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_accelerators() {
+        for accelerator in [
+            "CmdOrCtrl+Shift+1",
+            "Alt+F12",
+            "ctrl + alt + pageup",
+            "Super+Numpad5",
+            "Shift+/",
+            "F5",
+        ] {
+            assert_eq!(validate_accelerator(accelerator), Ok(()), "{}", accelerator);
+        }
+    }
+
+    #[test]
+    fn rejects_missing_keys() {
+        assert!(validate_accelerator("Ctrl+Shift+").is_err());
+        assert!(validate_accelerator("Ctrl+Shift").is_err());
+        assert!(validate_accelerator("Ctrl++1").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_modifiers() {
+        assert_eq!(
+            validate_accelerator("Hyper+1"),
+            Err("Invalid modifier HYPER in Hyper+1".to_string())
+        );
+        assert!(validate_accelerator("A+1").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(validate_accelerator("Ctrl+F36").is_err());
+        assert!(validate_accelerator("Ctrl+AB").is_err());
+    }
+
+    #[test]
+    fn rejects_duplicate_modifiers() {
+        assert_eq!(
+            validate_accelerator("Ctrl+Ctrl+1"),
+            Err("Duplicate modifier CTRL in Ctrl+Ctrl+1".to_string())
+        );
+        assert!(validate_accelerator("Ctrl+Control+1").is_err());
+        assert!(validate_accelerator("Option+Alt+1").is_err());
+    }
+
+    #[test]
+    fn rejects_empty_accelerators() {
+        assert!(validate_accelerator("").is_err());
+        assert!(validate_accelerator(" ").is_err());
+    }
+}
@@ -1,16 +1,76 @@
-import { useState, useEffect } from "preact/hooks";
+import { useState, useEffect, useRef } from "preact/hooks";
 
 import { listen } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/tauri";
 import { FunctionComponent } from "preact";
 
+interface VcpValue {
+    current: number;
+    maximum: number;
+}
+
+type Control = "brightness" | "contrast" | "volume" | "treble" | "bass";
+
 interface MonitorInfo {
-    id: number;
+    id: string;
     model: string;
     inputs: string[];
+    input_names: { [input: string]: string };
+    current_input: string | null;
+    brightness: VcpValue | null;
+    contrast: VcpValue | null;
+    volume: VcpValue | null;
+    muted: boolean | null;
+    treble: VcpValue | null;
+    bass: VcpValue | null;
+    capabilities:
+        | { status: "available" }
+        | { status: "unavailable"; reason: string };
+}
+
+type IndexedMonitorInfo = { [id: string]: MonitorInfo };
+
+interface MonitorError {
+    kind: string;
+    message?: string;
+    code?: number;
+    context?: string;
+    id?: string;
+    input?: string;
+    address?: number;
+    name?: string;
 }
 
-type IndexedMonitorInfo = { [id: number]: MonitorInfo };
+const describeError = (error: MonitorError): string => {
+    switch (error.kind) {
+        case "permission_denied":
+            return `Permission denied: ${error.message}`;
+        case "timeout":
+            return "The monitor didn't respond";
+        case "protocol":
+            return `Invalid reply from the monitor: ${error.message}`;
+        case "unsupported_feature":
+            return `The monitor doesn't support VCP feature 0x${error.code
+                ?.toString(16)
+                .toUpperCase()}`;
+        case "unsupported_source_address":
+            return `Writing from source address 0x${error.address
+                ?.toString(16)
+                .toUpperCase()} isn't supported on this platform`;
+        case "capability_parse":
+            return `Unable to read capabilities, ${error.context}`;
+        case "monitor_not_found":
+            return `Monitor ${error.id} is no longer connected`;
+        case "invalid_input":
+            return `Unknown input ${error.input}`;
+        case "busy":
+            return "The monitor is busy";
+        case "profile_not_found":
+            return `Profile ${error.name} no longer exists`;
+        default:
+            return error.message ?? "Unknown error";
+    }
+};
 
 interface MonitorShortcutProps {
     shortcut: string[];
@@ -18,16 +78,67 @@ interface MonitorShortcutProps {
 
 const MonitorShortcut = () => {};
 
+interface ControlSliderProps {
+    label: string;
+    value: VcpValue;
+    onChange: (value: number) => void;
+}
+
+const ControlSlider: FunctionComponent<ControlSliderProps> = ({
+    label,
+    value,
+    onChange,
+}) => {
+    const [current, setCurrent] = useState(value.current);
+
+    useEffect(() => setCurrent(value.current), [value.current]);
+
+    return (
+        <div className="monitor__info__control">
+            <div className="monitor__info__control__label">{label}</div>
+            <input
+                className="monitor__info__control__slider"
+                type="range"
+                min={0}
+                max={value.maximum}
+                value={current}
+                onInput={(e) => {
+                    const value = Number(e.currentTarget.value);
+                    setCurrent(value);
+                    onChange(value);
+                }}
+            />
+            <div className="monitor__info__control__value">{current}</div>
+        </div>
+    );
+};
+
+const SLIDERS: [Control, string][] = [
+    ["brightness", "Brightness"],
+    ["contrast", "Contrast"],
+    ["volume", "Volume"],
+    ["treble", "Treble"],
+    ["bass", "Bass"],
+];
+
 interface MonitorInfoListProps {
     monitor: MonitorInfo;
+    index: number;
     toggled: boolean;
-    onToggle: (id: number, toggled: boolean) => void;
+    onToggle: (id: string, toggled: boolean) => void;
+    onSwitch: (id: string, input: string) => void;
+    onAdjust: (id: string, control: Control, value: number) => void;
+    onMute: (id: string, muted: boolean) => void;
 }
 
 const MonitorInfoList: FunctionComponent<MonitorInfoListProps> = ({
     monitor,
+    index,
     toggled,
     onToggle,
+    onSwitch,
+    onAdjust,
+    onMute,
 }) => {
     return (
         <div className="monitor">
@@ -35,7 +146,15 @@ const MonitorInfoList: FunctionComponent<MonitorInfoListProps> = ({
                 className="monitor__header"
                 onClick={() => onToggle(monitor.id, !toggled)}
             >
-                {monitor.id + 1}. {monitor.model}
+                {index + 1}. {monitor.model}
+                {monitor.capabilities.status === "unavailable" && (
+                    <span
+                        className="monitor__header__warning"
+                        title={monitor.capabilities.reason}
+                    >
+                        capabilities unavailable
+                    </span>
+                )}
             </div>
             <div
                 className="monitor__info"
@@ -44,18 +163,45 @@ const MonitorInfoList: FunctionComponent<MonitorInfoListProps> = ({
                 {monitor.inputs.map((input) => (
                     <div className="monitor__info__input">
                         <div
-                            onClick={() => {
-                                invoke("switch_monitor_input", {
-                                    monitorIdx: monitor.id,
-                                    input,
-                                });
-                            }}
-                            className="monitor__info__input__name"
+                            onClick={() => onSwitch(monitor.id, input)}
+                            className={
+                                input === monitor.current_input
+                                    ? "monitor__info__input__name monitor__info__input__name--active"
+                                    : "monitor__info__input__name"
+                            }
                         >
-                            {input}
+                            {monitor.input_names[input] ?? input}
                         </div>
                     </div>
                 ))}
+                {SLIDERS.map(([control, label]) => {
+                    const value = monitor[control];
+
+                    return (
+                        value && (
+                            <ControlSlider
+                                key={control}
+                                label={label}
+                                value={value}
+                                onChange={(value) =>
+                                    onAdjust(monitor.id, control, value)
+                                }
+                            />
+                        )
+                    );
+                })}
+                {monitor.muted !== null && (
+                    <label className="monitor__info__mute">
+                        <input
+                            type="checkbox"
+                            checked={monitor.muted}
+                            onChange={(e) =>
+                                onMute(monitor.id, e.currentTarget.checked)
+                            }
+                        />
+                        Mute
+                    </label>
+                )}
             </div>
         </div>
     );
@@ -63,16 +209,24 @@ const MonitorInfoList: FunctionComponent<MonitorInfoListProps> = ({
 
 interface MonitorListProps {
     monitors: IndexedMonitorInfo;
+    onSwitch: (id: string, input: string) => void;
+    onAdjust: (id: string, control: Control, value: number) => void;
+    onMute: (id: string, muted: boolean) => void;
 }
 
-const MonitorList: FunctionComponent<MonitorListProps> = ({ monitors }) => {
+const MonitorList: FunctionComponent<MonitorListProps> = ({
+    monitors,
+    onSwitch,
+    onAdjust,
+    onMute,
+}) => {
     const [toggledMonitors, setToggledMonitors] = useState<{
-        [key: number]: boolean;
+        [key: string]: boolean;
     }>({});
 
     useEffect(() => {
         const toggledIds = Object.values(monitors).reduce(
-            (ids: { [key: number]: boolean }, monitor) => {
+            (ids: { [key: string]: boolean }, monitor) => {
                 ids[monitor.id] = false;
                 return ids;
             },
@@ -84,11 +238,12 @@ const MonitorList: FunctionComponent<MonitorListProps> = ({ monitors }) => {
 
     return (
         <div className="monitor-list">
-            {Object.values(monitors).map((monitor) => {
+            {Object.values(monitors).map((monitor, index) => {
                 return (
                     <MonitorInfoList
                         key={monitor.id}
                         monitor={monitor}
+                        index={index}
                         toggled={toggledMonitors[monitor.id]}
                         onToggle={(id, toggled) => {
                             setToggledMonitors({
@@ -96,6 +251,9 @@ const MonitorList: FunctionComponent<MonitorListProps> = ({ monitors }) => {
                                 [id]: toggled,
                             });
                         }}
+                        onSwitch={onSwitch}
+                        onAdjust={onAdjust}
+                        onMute={onMute}
                     />
                 );
             })}
@@ -105,11 +263,59 @@ const MonitorList: FunctionComponent<MonitorListProps> = ({ monitors }) => {
 
 export function App() {
     const [monitors, setMonitors] = useState<IndexedMonitorInfo>({});
+    const [error, setError] = useState<string | null>(null);
+
+    const switchInput = (monitorId: string, input: string) => {
+        setError(null);
+
+        invoke("switch_monitor_input", { monitorId, input })
+            .then(() => invoke("refresh_monitor_info"))
+            .catch((e: MonitorError) => setError(describeError(e)));
+    };
+
+    // Numbers the adjustments so the backend can drop the ones that arrive late
+    const adjustmentSequence = useRef(0);
+
+    // Sent on every slider step, the backend folds queued writes together
+    const adjustControl = (
+        monitorId: string,
+        control: Control,
+        value: number
+    ) => {
+        adjustmentSequence.current += 1;
+
+        invoke("adjust_monitor_control", {
+            monitorId,
+            control,
+            adjustment: { absolute: value },
+            sequence: adjustmentSequence.current,
+        }).catch((e: MonitorError) => setError(describeError(e)));
+    };
+
+    const setMute = (monitorId: string, muted: boolean) => {
+        invoke("set_monitor_mute", { monitorId, muted })
+            .then(() =>
+                setMonitors((monitors) => ({
+                    ...monitors,
+                    [monitorId]: { ...monitors[monitorId], muted },
+                }))
+            )
+            .catch((e: MonitorError) => setError(describeError(e)));
+    };
 
     useEffect(() => {
         invoke("refresh_monitor_info");
     }, []);
 
+    useEffect(() => {
+        // Problems found before the window was there to show them
+        invoke<string[]>("take_startup_errors").then((errors) => {
+            if (errors.length > 0) {
+                setError(errors.join("\n"));
+            }
+        });
+    }, []);
+
     useEffect(() => {
         async function parseMonitorInfoEvent() {
             const listener = await listen<MonitorInfo[]>(
@@ -132,6 +338,17 @@ export function App() {
         parseMonitorInfoEvent();
     }, []);
 
+    useEffect(() => {
+        // Failures of actions that weren't started from the window, e.g. shortcuts
+        const unlisten = listen<MonitorError>("monitor-error", (event) =>
+            setError(describeError(event.payload))
+        );
+
+        return () => {
+            unlisten.then((f) => f());
+        };
+    }, []);
+
     return (
         <div className="app">
             <div className="app__header">
@@ -159,8 +376,18 @@ export function App() {
                     </svg>
                 </div>
             </div>
+            {error && (
+                <div className="app__error" onClick={() => setError(null)}>
+                    {error}
+                </div>
+            )}
             <div className="app__content">
-                <MonitorList monitors={monitors} />
+                <MonitorList
+                    monitors={monitors}
+                    onSwitch={switchInput}
+                    onAdjust={adjustControl}
+                    onMute={setMute}
+                />
             </div>
         </div>
     );